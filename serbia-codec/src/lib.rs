@@ -0,0 +1,248 @@
+//! Runtime support for custom array encodings in [serbia](https://docs.rs/serbia).
+//!
+//! Implement [ArrayCodec] for a type of your own and point *Serbia* at it with
+//! `#[serbia(codec = "...")]` to (de)serialize a big array field any way you like.
+//!
+//! ```rust
+//! use serbia::serbia;
+//! use serbia_codec::ArrayCodec;
+//! use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+//!
+//! /// Encodes a byte array as a hex string.
+//! struct Hex;
+//!
+//! impl<const N: usize> ArrayCodec<u8, N> for Hex {
+//!     fn serialize<S>(array: &[u8; N], serializer: S) -> Result<S::Ok, S::Error>
+//!     where
+//!         S: Serializer,
+//!     {
+//!         let hex: String = array.iter().map(|b| format!("{:02x}", b)).collect();
+//!         serializer.serialize_str(&hex)
+//!     }
+//!
+//!     fn deserialize<'de, D>(deserializer: D) -> Result<[u8; N], D::Error>
+//!     where
+//!         D: Deserializer<'de>,
+//!     {
+//!         let hex = String::deserialize(deserializer)?;
+//!         if !hex.is_ascii() {
+//!             return Err(D::Error::custom("expected a hex string"));
+//!         }
+//!         if hex.len() != N * 2 {
+//!             return Err(D::Error::invalid_length(hex.len(), &"a hex string"));
+//!         }
+//!
+//!         let mut arr = [0; N];
+//!         for (i, b) in arr.iter_mut().enumerate() {
+//!             *b = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(D::Error::custom)?;
+//!         }
+//!         Ok(arr)
+//!     }
+//! }
+//!
+//! #[serbia]
+//! #[derive(Serialize, Deserialize)]
+//! struct S {
+//!     #[serbia(codec = "Hex")]
+//!     key: [u8; 64],
+//! }
+//! ```
+
+use std::convert::TryInto;
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, SeqAccess, Visitor};
+use serde::ser::SerializeTuple;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A custom way to (de)serialize an array of `N` elements of type `T`.
+///
+/// Implementors don't need to be constructible. They are only used to pick
+/// the implementation, much like a module passed to `#[serde(with = "...")]`.
+pub trait ArrayCodec<T, const N: usize> {
+    fn serialize<S>(array: &[T; N], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer;
+
+    fn deserialize<'de, D>(deserializer: D) -> Result<[T; N], D::Error>
+    where
+        D: Deserializer<'de>;
+}
+
+/// The encoding *Serbia* uses for big arrays by default: a tuple of `N` elements,
+/// just like Serde's own arrays. Custom codecs can fall back to it.
+///
+/// ```rust
+/// use serbia_codec::{ArrayCodec, Tuple};
+/// use serde::{Deserializer, Serializer};
+///
+/// /// Encodes a byte array as a string in human readable formats only.
+/// struct StringInText;
+///
+/// impl<const N: usize> ArrayCodec<u8, N> for StringInText {
+///     fn serialize<S>(array: &[u8; N], serializer: S) -> Result<S::Ok, S::Error>
+///     where
+///         S: Serializer,
+///     {
+///         if serializer.is_human_readable() {
+///             // ...
+///             # unimplemented!()
+///         } else {
+///             Tuple::serialize(array, serializer)
+///         }
+///     }
+///
+///     fn deserialize<'de, D>(deserializer: D) -> Result<[u8; N], D::Error>
+///     where
+///         D: Deserializer<'de>,
+///     {
+///         if deserializer.is_human_readable() {
+///             // ...
+///             # unimplemented!()
+///         } else {
+///             Tuple::deserialize(deserializer)
+///         }
+///     }
+/// }
+/// ```
+pub struct Tuple;
+
+impl<T, const N: usize> ArrayCodec<T, N> for Tuple
+where
+    T: Serialize + DeserializeOwned,
+{
+    fn serialize<S>(array: &[T; N], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_tuple(N)?;
+        for e in array {
+            seq.serialize_element(e)?;
+        }
+        seq.end()
+    }
+
+    fn deserialize<'de, D>(deserializer: D) -> Result<[T; N], D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct TupleVisitor<T, const N: usize>(PhantomData<T>);
+
+        impl<'de, T, const N: usize> Visitor<'de> for TupleVisitor<T, N>
+        where
+            T: DeserializeOwned,
+        {
+            type Value = [T; N];
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "an array of length {}", N)
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut elems = Vec::with_capacity(N);
+                while elems.len() < N {
+                    match seq.next_element()? {
+                        Some(e) => elems.push(e),
+                        None => return Err(de::Error::invalid_length(elems.len(), &self)),
+                    }
+                }
+
+                match elems.try_into() {
+                    Ok(arr) => Ok(arr),
+                    Err(_) => unreachable!(),
+                }
+            }
+        }
+
+        deserializer.deserialize_tuple(N, TupleVisitor(PhantomData))
+    }
+}
+
+/// Deserializes an array in the default encoding into an existing one, overwriting
+/// it element by element with `Deserialize::deserialize_in_place`, so that elements
+/// can reuse their allocations. If it fails, the array is left partially overwritten.
+///
+/// *Serde*'s derived `deserialize_in_place` can't do this for fields *Serbia*
+/// handles, so this is meant for hand-written decoding loops.
+///
+/// ```rust
+/// let mut pixels = [0u32; 76800];
+/// let frame = serde_json::to_string(&vec![7u32; 76800]).unwrap();
+///
+/// let mut deserializer = serde_json::Deserializer::from_str(&frame);
+/// serbia_codec::deserialize_in_place(&mut pixels, &mut deserializer).unwrap();
+/// assert_eq!(pixels[76799], 7);
+/// ```
+pub fn deserialize_in_place<'de, T, D, const N: usize>(
+    place: &mut [T; N],
+    deserializer: D,
+) -> Result<(), D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    InPlace(place).deserialize(deserializer)
+}
+
+/// A [DeserializeSeed] that deserializes an array into an existing one, like
+/// [deserialize_in_place]. It can be used from a hand-written `Visitor`, like one
+/// deserializing a struct in place.
+pub struct InPlace<'a, T, const N: usize>(pub &'a mut [T; N]);
+
+impl<'a, 'de, T, const N: usize> DeserializeSeed<'de> for InPlace<'a, T, N>
+where
+    T: Deserialize<'de>,
+{
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_tuple(N, self)
+    }
+}
+
+impl<'a, 'de, T, const N: usize> Visitor<'de> for InPlace<'a, T, N>
+where
+    T: Deserialize<'de>,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "an array of length {}", N)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        for (i, place) in self.0.iter_mut().enumerate() {
+            if seq.next_element_seed(ElementInPlace(place))?.is_none() {
+                return Err(de::Error::invalid_length(i, &self));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Deserializes a single element in place.
+struct ElementInPlace<'a, T>(&'a mut T);
+
+impl<'a, 'de, T> DeserializeSeed<'de> for ElementInPlace<'a, T>
+where
+    T: Deserialize<'de>,
+{
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        T::deserialize_in_place(deserializer, self.0)
+    }
+}
@@ -0,0 +1,618 @@
+mod big_tuple;
+mod borrowed;
+mod codec;
+mod content;
+mod delimited;
+mod delta;
+mod packed;
+mod rows;
+mod text;
+mod tuple;
+
+pub use content::render_lenient_mod;
+pub use packed::Endianness;
+pub use tuple::Pointer;
+
+use proc_macro2::{Group, Ident, Span, TokenStream, TokenTree};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::spanned::Spanned;
+use syn::{Generics, Lifetime, Lit, LitStr, Type};
+
+use crate::item::BigArrayField;
+
+/// The representation a big array field is (de)serialized as.
+#[derive(Default)]
+pub enum Encoding {
+    /// A plain tuple of elements. This is what Serde does for small arrays.
+    #[default]
+    Tuple,
+    /// The first element followed by differences between neighbors. Only works
+    /// for primitive integer element types. Deltas of signed types are zigzag encoded,
+    /// and so are those of unsigned types if `zigzag` is set, which lets them decrease.
+    Delta { zigzag: bool },
+    /// A single byte string of fixed-width elements in the given byte order.
+    /// Only works for primitive numeric element types.
+    Packed(Endianness),
+    /// A string stored in a NUL-padded byte buffer. If `nul_terminated` is set,
+    /// the buffer must also contain a terminating NUL, like a C string.
+    Text { nul_terminated: bool },
+    /// A sequence of rows of `row_len` elements, the last one possibly shorter.
+    /// Only used for human-readable formats, unless `in_binary` is set.
+    Rows {
+        row_len: TokenStream,
+        in_binary: bool,
+    },
+    /// A single string of elements separated by `delimiter`, using the elements'
+    /// `Display` and `FromStr` implementations.
+    Delimited { delimiter: LitStr },
+    /// Whatever the given implementor of `serbia_codec::ArrayCodec` does.
+    Codec(Box<Type>),
+}
+
+impl Encoding {
+    pub fn is_default(&self) -> bool {
+        matches!(self, Encoding::Tuple)
+    }
+
+    /// Where predicates a generic element type `ty` needs to satisfy to be serialized.
+    fn serialize_bounds(&self, ty: &Ident, len: &TokenStream) -> TokenStream {
+        match self {
+            Encoding::Delimited { .. } => quote! { #ty: std::fmt::Display },
+            Encoding::Codec(codec) => quote! { #codec: serbia_codec::ArrayCodec<#ty, #len> },
+            _ => quote! { #ty: serde::Serialize },
+        }
+    }
+
+    /// Where predicates a generic element type `ty` needs to satisfy to be deserialized.
+    fn deserialize_bounds(&self, ty: &Ident, len: &TokenStream) -> TokenStream {
+        match self {
+            Encoding::Codec(codec) => quote! { #codec: serbia_codec::ArrayCodec<#ty, #len> },
+            Encoding::Delimited { .. } => quote! {
+                #ty: std::str::FromStr,
+                <#ty as std::str::FromStr>::Err: std::fmt::Display
+            },
+            _ => quote! { #ty: for<'d> serde::Deserialize<'d> },
+        }
+    }
+}
+
+/// Named lifetimes a type refers to, other than `'static`.
+pub fn lifetimes(ty: &Type) -> Vec<Lifetime> {
+    fn collect(tokens: TokenStream, lifetimes: &mut Vec<Lifetime>) {
+        let mut tokens = tokens.into_iter();
+        while let Some(token) = tokens.next() {
+            match token {
+                TokenTree::Group(group) => collect(group.stream(), lifetimes),
+                TokenTree::Punct(punct) if punct.as_char() == '\'' => {
+                    if let Some(TokenTree::Ident(ident)) = tokens.next() {
+                        let lifetime = Lifetime {
+                            apostrophe: punct.span(),
+                            ident,
+                        };
+                        if lifetime.ident != "static"
+                            && lifetime.ident != "_"
+                            && !lifetimes.contains(&lifetime)
+                        {
+                            lifetimes.push(lifetime);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut lifetimes = vec![];
+    collect(ty.to_token_stream(), &mut lifetimes);
+    lifetimes
+}
+
+/// Type parameters out of `generics` that a type refers to.
+pub fn type_params<'g>(ty: &Type, generics: &'g Generics) -> Vec<&'g Ident> {
+    fn mentions(tokens: TokenStream, param: &Ident) -> bool {
+        tokens.into_iter().any(|token| match token {
+            TokenTree::Group(group) => mentions(group.stream(), param),
+            TokenTree::Ident(ident) => &ident == param,
+            _ => false,
+        })
+    }
+
+    generics
+        .type_params()
+        .map(|param| &param.ident)
+        .filter(|param| mentions(ty.to_token_stream(), param))
+        .collect()
+}
+
+/// Renders a compile-time check that `ty` is an array of `len` elements. If it's not,
+/// the error is reported at `span`, starting with `what`, like "bufsize = 300 but the
+/// field type".
+pub fn render_len_check(ty: &Type, len: &TokenStream, span: Span, what: &str) -> TokenStream {
+    // Everything is respanned so that the error doesn't point at the type instead.
+    fn respan(tokens: TokenStream, span: Span) -> TokenStream {
+        tokens
+            .into_iter()
+            .map(|mut token| {
+                if let TokenTree::Group(group) = &token {
+                    let mut respanned = Group::new(group.delimiter(), respan(group.stream(), span));
+                    respanned.set_span(span);
+                    token = TokenTree::Group(respanned);
+                }
+                token.set_span(span);
+                token
+            })
+            .collect()
+    }
+
+    let message = format!("{} is `{{Self}}`", what);
+    let label = format!("expected an array of {} elements", len);
+    let ty = respan(ty.to_token_stream(), span);
+
+    quote_spanned! {span=>
+        const _: () = {
+            #[diagnostic::on_unimplemented(message = #message, label = #label)]
+            trait SerbiaLen {}
+
+            impl<T> SerbiaLen for [T; #len] {}
+
+            fn check<T: SerbiaLen>() {}
+
+            #[allow(dead_code)]
+            fn check_len() {
+                check::<#ty>();
+            }
+        };
+    }
+}
+
+/// Renders a constant `ident` holding the length of the array type `ty`. Unlike an
+/// expression, its name can be used anywhere a length can, including as a const
+/// generic argument.
+pub fn render_len_const(ident: &Ident, ty: &Type) -> TokenStream {
+    quote! {
+        #[allow(non_upper_case_globals)]
+        const #ident: usize = {
+            const fn len<T, const N: usize>(_: *const [T; N]) -> usize {
+                N
+            }
+            len(core::ptr::null::<#ty>())
+        };
+    }
+}
+
+/// Renders a compile-time check that a row length given as a constant isn't zero.
+/// Literal zeros are rejected while parsing the field.
+pub fn render_rows_check(field: &BigArrayField) -> Option<TokenStream> {
+    match &field.encoding {
+        Encoding::Rows { row_len, .. }
+            if !matches!(syn::parse2(row_len.clone()), Ok(Lit::Int(_))) =>
+        {
+            Some(quote_spanned! {row_len.span()=>
+                const _: () = core::assert!(#row_len > 0, "serbia(rows) must be greater than zero");
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Checks if a type contains a reference, which can only be deserialized by
+/// borrowing from the input.
+pub fn contains_reference(ty: &Type) -> bool {
+    fn check(tokens: TokenStream) -> bool {
+        tokens.into_iter().any(|token| match token {
+            TokenTree::Group(group) => check(group.stream()),
+            TokenTree::Punct(punct) => punct.as_char() == '&',
+            _ => false,
+        })
+    }
+
+    check(ty.to_token_stream())
+}
+
+/// Where predicates the field's generic element type `ty` needs to satisfy to be serialized.
+pub fn serialize_bounds(field: &BigArrayField, ty: &Ident) -> TokenStream {
+    field.encoding.serialize_bounds(ty, &field.len)
+}
+
+/// Where predicates the field's generic element type `ty` needs to satisfy to be deserialized.
+pub fn deserialize_bounds(field: &BigArrayField, ty: &Ident) -> TokenStream {
+    let bounds = field.encoding.deserialize_bounds(ty, &field.len);
+
+    let bounds =
+        if field.default_on_element_error || (field.default && field.default_value.is_none()) {
+            quote! { #bounds, #ty: core::default::Default }
+        } else {
+            bounds
+        };
+
+    if field.zeroize {
+        quote! { #bounds, #ty: zeroize::Zeroize }
+    } else {
+        bounds
+    }
+}
+
+pub fn render_serialize_fn(fn_ident: &Ident, field: &BigArrayField) -> TokenStream {
+    if !field.optional {
+        return render_array_serialize_fn(fn_ident, field);
+    }
+
+    let inner_fn_ident = format_ident!("{}_some", fn_ident);
+    let inner_fn = render_array_serialize_fn(&inner_fn_ident, field);
+    let ty = &field.ty;
+
+    quote! {
+        fn #fn_ident<S>(value: &core::option::Option<#ty>, serializer: S) -> core::result::Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            #inner_fn
+
+            struct SomeArray<'serbia>(&'serbia #ty);
+
+            impl serde::Serialize for SomeArray<'_> {
+                fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+                where
+                    S: serde::Serializer,
+                {
+                    #inner_fn_ident(self.0, serializer)
+                }
+            }
+
+            match value {
+                core::option::Option::Some(arr) => serializer.serialize_some(&SomeArray(arr)),
+                core::option::Option::None => serializer.serialize_none(),
+            }
+        }
+    }
+}
+
+fn render_array_serialize_fn(fn_ident: &Ident, field: &BigArrayField) -> TokenStream {
+    check_element_options(field);
+
+    if let Some(tuple_types) = &field.tuple_types {
+        return big_tuple::render_serialize_fn(fn_ident, tuple_types.len());
+    }
+
+    match &field.encoding {
+        // Borrowed byte arrays are serialized the way they can be borrowed back.
+        Encoding::Tuple
+            if field.pointer == Some(Pointer::Ref)
+                && field.element_type.as_ref().is_some_and(borrowed::is_u8) =>
+        {
+            borrowed::render_serialize_fn(fn_ident, &field.len)
+        }
+        Encoding::Tuple => tuple::render_serialize_fn(
+            fn_ident,
+            &field.len,
+            tuple::Element {
+                ty: field
+                    .element_serialize_with
+                    .as_ref()
+                    .map(|_| element_type(field)),
+                with: field.element_serialize_with.as_ref(),
+                pointer: field.pointer,
+                ..Default::default()
+            },
+        ),
+        Encoding::Delta { zigzag } => {
+            delta::render_serialize_fn(fn_ident, &field.len, delta_type(field), *zigzag)
+        }
+        Encoding::Packed(endianness) => {
+            packed::render_serialize_fn(fn_ident, &field.len, packed_type(field), endianness)
+        }
+        Encoding::Text { nul_terminated } => {
+            text::render_serialize_fn(fn_ident, &field.len, text_type(field), *nul_terminated)
+        }
+        Encoding::Rows { row_len, in_binary } => {
+            rows::render_serialize_fn(fn_ident, &field.len, row_len, *in_binary)
+        }
+        Encoding::Delimited { delimiter } => {
+            delimited::render_serialize_fn(fn_ident, &field.len, delimiter)
+        }
+        Encoding::Codec(codec) => codec::render_serialize_fn(fn_ident, &field.len, codec),
+    }
+}
+
+pub fn render_deserialize_fn(fn_ident: &Ident, field: &BigArrayField) -> TokenStream {
+    if !field.optional {
+        return render_array_deserialize_fn(fn_ident, field);
+    }
+
+    let inner_fn_ident = format_ident!("{}_some", fn_ident);
+    let inner_fn = render_array_deserialize_fn(&inner_fn_ident, field);
+    let ty = &field.ty;
+
+    quote! {
+        fn #fn_ident<'de, D>(deserializer: D) -> core::result::Result<core::option::Option<#ty>, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            #inner_fn
+
+            struct SomeArray(#ty);
+
+            impl<'de> serde::Deserialize<'de> for SomeArray {
+                fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+                where
+                    D: serde::Deserializer<'de>,
+                {
+                    #inner_fn_ident(deserializer).map(SomeArray)
+                }
+            }
+
+            let value: core::option::Option<SomeArray> = serde::Deserialize::deserialize(deserializer)?;
+            Ok(value.map(|arr| arr.0))
+        }
+    }
+}
+
+fn render_array_deserialize_fn(fn_ident: &Ident, field: &BigArrayField) -> TokenStream {
+    check_element_options(field);
+
+    // Elements of tuple encoded arrays are validated as soon as they're deserialized.
+    // Everything else is validated once the whole array is deserialized.
+    let validate_early = field.encoding.is_default()
+        && field.element_type.is_some()
+        && field.pointer != Some(Pointer::Ref);
+    let validate_element_late = field.validate_element.as_ref().filter(|_| !validate_early);
+
+    if field.validate.is_none() && validate_element_late.is_none() {
+        return render_unvalidated_deserialize_fn(fn_ident, field);
+    }
+
+    let inner_fn_ident = format_ident!("{}_unvalidated", fn_ident);
+    let inner_fn = render_unvalidated_deserialize_fn(&inner_fn_ident, field);
+    let field_ty = &field.ty;
+
+    let (arr_binding, wipe) = if field.zeroize {
+        (
+            quote! { mut arr },
+            quote! { zeroize::Zeroize::zeroize(&mut arr); },
+        )
+    } else {
+        (quote! { arr }, quote! {})
+    };
+    let validate_elements = validate_element_late.map(|path| {
+        quote! {
+            for (i, val) in arr.iter().enumerate() {
+                if let Err(err) = #path(i, val) {
+                    #wipe
+                    return Err(serde::de::Error::custom(std::format_args!(
+                        "invalid element at index {}: {}",
+                        i,
+                        err,
+                    )));
+                }
+            }
+        }
+    });
+    let validate = field.validate.as_ref().map(|path| {
+        quote! {
+            if let Err(err) = #path(&arr) {
+                #wipe
+                return Err(serde::de::Error::custom(std::format_args!("invalid array: {}", err)));
+            }
+        }
+    });
+
+    // Lifetimes of the field type have to be declared, and outlived by the input
+    // if the field borrows from it.
+    let lifetimes = lifetimes(field_ty);
+    let outlives = if field.borrow {
+        quote! { #('de: #lifetimes,)* }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        fn #fn_ident<'de, #(#lifetimes,)* D>(deserializer: D) -> core::result::Result<#field_ty, D::Error>
+        where
+            #outlives
+            D: serde::Deserializer<'de>,
+        {
+            #inner_fn
+
+            let #arr_binding: #field_ty = #inner_fn_ident(deserializer)?;
+            #validate_elements
+            #validate
+            Ok(arr)
+        }
+    }
+}
+
+fn render_unvalidated_deserialize_fn(fn_ident: &Ident, field: &BigArrayField) -> TokenStream {
+    if let Some(tuple_types) = &field.tuple_types {
+        return big_tuple::render_deserialize_fn(fn_ident, tuple_types.len());
+    }
+
+    match &field.encoding {
+        Encoding::Tuple if field.pointer == Some(Pointer::Ref) => {
+            check_borrowed(field);
+            borrowed::render_deserialize_fn(fn_ident, &field.len)
+        }
+        Encoding::Tuple => {
+            // Validating elements early needs the element type, which is unknown
+            // for type aliases.
+            let validate = field
+                .validate_element
+                .as_ref()
+                .filter(|_| field.element_type.is_some());
+            let borrow_cow = field.borrow
+                && field
+                    .element_type
+                    .as_ref()
+                    .is_some_and(tuple::is_borrowable_cow);
+            let concrete =
+                field.element_deserialize_with.is_some() || validate.is_some() || borrow_cow;
+
+            tuple::render_deserialize_fn(
+                fn_ident,
+                &field.len,
+                tuple::Element {
+                    ty: concrete.then(|| element_type(field)),
+                    with: field.element_deserialize_with.as_ref(),
+                    validate,
+                    default_on_error: field.lenient_mod.as_ref(),
+                    error_hook: field.element_error_hook.as_ref(),
+                    safe: field.safe,
+                    zeroize: field.zeroize,
+                    pointer: field.pointer,
+                    borrow: field.borrow,
+                },
+            )
+        }
+        Encoding::Delta { zigzag } => {
+            delta::render_deserialize_fn(fn_ident, &field.len, delta_type(field), *zigzag)
+        }
+        Encoding::Packed(endianness) => {
+            packed::render_deserialize_fn(fn_ident, &field.len, packed_type(field), endianness)
+        }
+        Encoding::Text { nul_terminated } => {
+            text::render_deserialize_fn(fn_ident, &field.len, text_type(field), *nul_terminated)
+        }
+        Encoding::Rows { row_len, in_binary } => {
+            rows::render_deserialize_fn(fn_ident, &field.len, row_len, *in_binary, field.safe)
+        }
+        Encoding::Delimited { delimiter } => {
+            delimited::render_deserialize_fn(fn_ident, &field.len, delimiter)
+        }
+        Encoding::Codec(codec) => codec::render_deserialize_fn(fn_ident, &field.len, codec),
+    }
+}
+
+/// Renders a function returning the field's default value, for Serde's `default` attribute.
+pub fn render_default_fn(
+    fn_ident: &Ident,
+    field: &BigArrayField,
+    generics: &Generics,
+) -> TokenStream {
+    let field_ty = &field.ty;
+    let fill = match &field.default_value {
+        Some(value) => quote! { #value },
+        None => quote! { core::default::Default::default() },
+    };
+
+    let (value, element_types) = match &field.tuple_types {
+        Some(tuple_types) => {
+            if field.default_value.is_some() {
+                panic!("serbia(default) requires an array");
+            }
+            let fills = tuple_types.iter().map(|_| &fill);
+            (quote! { (#(#fills,)*) }, tuple_types.iter().collect())
+        }
+        None => {
+            let len = &field.len;
+            let value = match field.pointer {
+                None => quote! { core::array::from_fn(|_| #fill) },
+                Some(Pointer::Ref) => panic!("borrowed arrays can't have a default"),
+                // Built straight on the heap, like when deserializing.
+                Some(_) => quote! {
+                    let vec: std::vec::Vec<_> = (0..#len).map(|_| #fill).collect();
+                    let arr: std::boxed::Box<[_; #len]> = match core::convert::TryInto::try_into(vec.into_boxed_slice()) {
+                        Ok(arr) => arr,
+                        Err(_) => unreachable!(),
+                    };
+                    core::convert::From::from(arr)
+                },
+            };
+            (value, vec![element_type(field)])
+        }
+    };
+
+    // Generic element types need to be defaultable, unless there's a fill value.
+    let bounds = element_types
+        .into_iter()
+        .filter(|ty| field.default_value.is_none() && !type_params(ty, generics).is_empty());
+    let lifetimes = lifetimes(field_ty);
+    let params = type_params(field_ty, generics);
+
+    quote! {
+        fn #fn_ident<#(#lifetimes,)* #(#params,)*>() -> #field_ty
+        where
+            #(#bounds: core::default::Default,)*
+        {
+            #value
+        }
+    }
+}
+
+fn check_element_options(field: &BigArrayField) {
+    let has_element_with =
+        field.element_serialize_with.is_some() || field.element_deserialize_with.is_some();
+
+    if field.tuple_types.is_some()
+        && (!field.encoding.is_default()
+            || has_element_with
+            || field.validate_element.is_some()
+            || field.default_on_element_error
+            || field.element_error_hook.is_some()
+            || field.zeroize)
+    {
+        panic!(
+            "big tuples can only be combined with serbia(validate) and serbia(skip) and friends"
+        );
+    }
+
+    if has_element_with && !field.encoding.is_default() {
+        panic!("serbia(element_with) and friends can't be combined with a custom encoding");
+    }
+    if field.default_on_element_error && !field.encoding.is_default() {
+        panic!("serbia(on_element_error) can't be combined with a custom encoding");
+    }
+    if field.zeroize && !field.encoding.is_default() {
+        panic!("serbia(zeroize) can't be combined with a custom encoding");
+    }
+    if field.zeroize && field.safe {
+        panic!("serbia(zeroize) can't be combined with safe code generation");
+    }
+    if field.pointer.is_some() && !field.encoding.is_default() {
+        panic!(
+            "arrays behind a reference, Box, Rc or Arc can't be combined with a custom encoding"
+        );
+    }
+    if field.pointer.is_some() && field.zeroize {
+        panic!("serbia(zeroize) can't be combined with arrays behind a reference, Box, Rc or Arc");
+    }
+}
+
+/// Checks that a borrowed array can be deserialized.
+fn check_borrowed(field: &BigArrayField) {
+    if !field.element_type.as_ref().is_some_and(borrowed::is_u8) {
+        panic!("only byte arrays (&[u8; N]) can be borrowed from the input");
+    }
+    if field.element_deserialize_with.is_some() || field.default_on_element_error {
+        panic!("serbia(element_with) and friends can't be combined with borrowed arrays");
+    }
+}
+
+fn element_type<'a>(field: &'a BigArrayField) -> &'a Type {
+    field
+        .element_type
+        .as_ref()
+        .expect("serbia(element_with) and friends require the field type to be an array")
+}
+
+fn delta_type(field: &BigArrayField) -> delta::IntType {
+    field
+        .element_type
+        .as_ref()
+        .and_then(delta::IntType::parse)
+        .expect("serbia(delta) requires an array of a primitive integer type")
+}
+
+fn packed_type(field: &BigArrayField) -> packed::NumType {
+    field
+        .element_type
+        .as_ref()
+        .and_then(packed::NumType::parse)
+        .expect("serbia(packed) requires an array of a fixed-width primitive numeric type")
+}
+
+fn text_type<'a>(field: &'a BigArrayField) -> &'a Type {
+    field
+        .element_type
+        .as_ref()
+        .filter(|ty| text::is_byte_type(ty))
+        .expect("serbia(cstr) and serbia(utf8) require an array of u8, i8 or c_char")
+}
@@ -0,0 +1,74 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+use syn::Index;
+
+/// Generic parameters standing for the element types of a tuple of length `len`.
+fn type_params(len: usize) -> Vec<Ident> {
+    (0..len).map(|i| format_ident!("T{}", i)).collect()
+}
+
+pub fn render_serialize_fn(fn_ident: &Ident, len: usize) -> TokenStream {
+    let params = type_params(len);
+    let indices = (0..len).map(Index::from);
+
+    quote! {
+        fn #fn_ident<#(#params,)* S>(tuple: &(#(#params,)*), serializer: S) -> core::result::Result<S::Ok, S::Error>
+        where
+            #(#params: serde::Serialize,)*
+            S: serde::Serializer,
+        {
+            use serde::ser::SerializeTuple;
+
+            let mut seq = serializer.serialize_tuple(#len)?;
+            #(seq.serialize_element(&tuple.#indices)?;)*
+            seq.end()
+        }
+    }
+}
+
+pub fn render_deserialize_fn(fn_ident: &Ident, len: usize) -> TokenStream {
+    let params = type_params(len);
+    let values: Vec<_> = (0..len).map(|i| format_ident!("t{}", i)).collect();
+    let indices = 0..len;
+
+    quote! {
+        fn #fn_ident<'de, #(#params,)* D>(deserializer: D) -> core::result::Result<(#(#params,)*), D::Error>
+        where
+            #(#params: serde::Deserialize<'de>,)*
+            D: serde::Deserializer<'de>,
+        {
+            struct TupleVisitor<#(#params,)*> {
+                _casper: std::marker::PhantomData<(#(#params,)*)>,
+            }
+
+            impl<'de, #(#params,)*> serde::de::Visitor<'de> for TupleVisitor<#(#params,)*>
+            where
+                #(#params: serde::Deserialize<'de>,)*
+            {
+                type Value = (#(#params,)*);
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    std::write!(formatter, "a tuple of size {}", #len)
+                }
+
+                fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+                where
+                    A: serde::de::SeqAccess<'de>,
+                {
+                    #(
+                        let #values = match seq.next_element()? {
+                            Some(val) => val,
+                            None => return Err(serde::de::Error::invalid_length(#indices, &self)),
+                        };
+                    )*
+
+                    Ok((#(#values,)*))
+                }
+            }
+
+            deserializer.deserialize_tuple(#len, TupleVisitor {
+                _casper: std::marker::PhantomData,
+            })
+        }
+    }
+}
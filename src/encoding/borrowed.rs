@@ -0,0 +1,52 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{quote, ToTokens};
+use syn::{Type, TypePath};
+
+/// Checks if the type is `u8`, the only element type arrays can be borrowed with.
+pub fn is_u8(ty: &Type) -> bool {
+    if let Type::Path(TypePath { qself: None, path }) = ty {
+        return path.is_ident("u8");
+    }
+
+    false
+}
+
+pub fn render_serialize_fn(fn_ident: &Ident, len: impl ToTokens) -> TokenStream {
+    quote! {
+        fn #fn_ident<S>(array: &&[u8; #len], serializer: S) -> core::result::Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            serializer.serialize_bytes(&array[..])
+        }
+    }
+}
+
+pub fn render_deserialize_fn(fn_ident: &Ident, len: impl ToTokens) -> TokenStream {
+    quote! {
+        fn #fn_ident<'de, D>(deserializer: D) -> core::result::Result<&'de [u8; #len], D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            struct BorrowedVisitor;
+
+            impl<'de> serde::de::Visitor<'de> for BorrowedVisitor {
+                type Value = &'de [u8; #len];
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    std::write!(formatter, "{} borrowed bytes", #len)
+                }
+
+                fn visit_borrowed_bytes<Err>(self, v: &'de [u8]) -> Result<Self::Value, Err>
+                where
+                    Err: serde::de::Error,
+                {
+                    core::convert::TryInto::try_into(v)
+                        .map_err(|_| serde::de::Error::invalid_length(v.len(), &self))
+                }
+            }
+
+            deserializer.deserialize_bytes(BorrowedVisitor)
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{quote, ToTokens};
+use syn::Type;
+
+pub fn render_serialize_fn(fn_ident: &Ident, len: impl ToTokens, codec: &Type) -> TokenStream {
+    quote! {
+        fn #fn_ident<E, S>(array: &[E; #len], serializer: S) -> core::result::Result<S::Ok, S::Error>
+        where
+            #codec: serbia_codec::ArrayCodec<E, #len>,
+            S: serde::Serializer,
+        {
+            <#codec as serbia_codec::ArrayCodec<E, #len>>::serialize(array, serializer)
+        }
+    }
+}
+
+pub fn render_deserialize_fn(fn_ident: &Ident, len: impl ToTokens, codec: &Type) -> TokenStream {
+    quote! {
+        fn #fn_ident<'de, E, D>(deserializer: D) -> core::result::Result<[E; #len], D::Error>
+        where
+            #codec: serbia_codec::ArrayCodec<E, #len>,
+            D: serde::Deserializer<'de>,
+        {
+            <#codec as serbia_codec::ArrayCodec<E, #len>>::deserialize(deserializer)
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+
+/// Renders the module `ident` with what fields replacing invalid elements with defaults
+/// need. It's rendered once per container, and shared by all such fields.
+///
+/// `Content` is a buffer any value of a self-describing format can be read into and
+/// deserialized from again, along the lines of Serde's private one. Reading an element
+/// into it first means a bad element is consumed in full before it's found to be bad,
+/// instead of leaving the deserializer in the middle of it. `Lenient` deserializes an
+/// element through it, keeping the error instead of failing.
+pub fn render_lenient_mod(ident: &Ident) -> TokenStream {
+    quote! {
+        #[allow(non_snake_case)]
+        mod #ident {
+            pub enum Content<'de> {
+                Bool(bool),
+                U64(u64),
+                I64(i64),
+                F64(f64),
+                Char(char),
+                Str(&'de str),
+                String(std::string::String),
+                Bytes(&'de [u8]),
+                ByteBuf(std::vec::Vec<u8>),
+                None,
+                Some(std::boxed::Box<Content<'de>>),
+                Unit,
+                Newtype(std::boxed::Box<Content<'de>>),
+                Seq(std::vec::Vec<Content<'de>>),
+                Map(std::vec::Vec<(Content<'de>, Content<'de>)>),
+            }
+
+            impl<'de> serde::Deserialize<'de> for Content<'de> {
+                fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+                where
+                    D: serde::Deserializer<'de>,
+                {
+                    deserializer.deserialize_any(ContentVisitor)
+                }
+            }
+
+            pub struct ContentVisitor;
+
+            impl<'de> serde::de::Visitor<'de> for ContentVisitor {
+                type Value = Content<'de>;
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    formatter.write_str("any value")
+                }
+
+                fn visit_bool<Err>(self, v: bool) -> Result<Self::Value, Err> {
+                    Ok(Content::Bool(v))
+                }
+
+                fn visit_i64<Err>(self, v: i64) -> Result<Self::Value, Err> {
+                    Ok(Content::I64(v))
+                }
+
+                fn visit_u64<Err>(self, v: u64) -> Result<Self::Value, Err> {
+                    Ok(Content::U64(v))
+                }
+
+                fn visit_f64<Err>(self, v: f64) -> Result<Self::Value, Err> {
+                    Ok(Content::F64(v))
+                }
+
+                fn visit_char<Err>(self, v: char) -> Result<Self::Value, Err> {
+                    Ok(Content::Char(v))
+                }
+
+                fn visit_borrowed_str<Err>(self, v: &'de str) -> Result<Self::Value, Err> {
+                    Ok(Content::Str(v))
+                }
+
+                fn visit_str<Err>(self, v: &str) -> Result<Self::Value, Err> {
+                    Ok(Content::String(v.into()))
+                }
+
+                fn visit_string<Err>(self, v: std::string::String) -> Result<Self::Value, Err> {
+                    Ok(Content::String(v))
+                }
+
+                fn visit_borrowed_bytes<Err>(self, v: &'de [u8]) -> Result<Self::Value, Err> {
+                    Ok(Content::Bytes(v))
+                }
+
+                fn visit_bytes<Err>(self, v: &[u8]) -> Result<Self::Value, Err> {
+                    Ok(Content::ByteBuf(v.into()))
+                }
+
+                fn visit_byte_buf<Err>(self, v: std::vec::Vec<u8>) -> Result<Self::Value, Err> {
+                    Ok(Content::ByteBuf(v))
+                }
+
+                fn visit_none<Err>(self) -> Result<Self::Value, Err> {
+                    Ok(Content::None)
+                }
+
+                fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+                where
+                    D: serde::Deserializer<'de>,
+                {
+                    serde::Deserialize::deserialize(deserializer)
+                        .map(|v| Content::Some(std::boxed::Box::new(v)))
+                }
+
+                fn visit_unit<Err>(self) -> Result<Self::Value, Err> {
+                    Ok(Content::Unit)
+                }
+
+                fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+                where
+                    D: serde::Deserializer<'de>,
+                {
+                    serde::Deserialize::deserialize(deserializer)
+                        .map(|v| Content::Newtype(std::boxed::Box::new(v)))
+                }
+
+                fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+                where
+                    A: serde::de::SeqAccess<'de>,
+                {
+                    let mut elems = std::vec::Vec::new();
+                    while let Some(e) = seq.next_element()? {
+                        elems.push(e);
+                    }
+                    Ok(Content::Seq(elems))
+                }
+
+                fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+                where
+                    A: serde::de::MapAccess<'de>,
+                {
+                    let mut entries = std::vec::Vec::new();
+                    while let Some(entry) = map.next_entry()? {
+                        entries.push(entry);
+                    }
+                    Ok(Content::Map(entries))
+                }
+            }
+
+            impl<'de, Err> serde::de::IntoDeserializer<'de, Err> for Content<'de>
+            where
+                Err: serde::de::Error,
+            {
+                type Deserializer = ContentDeserializer<'de, Err>;
+
+                fn into_deserializer(self) -> Self::Deserializer {
+                    ContentDeserializer {
+                        content: self,
+                        _casper: std::marker::PhantomData,
+                    }
+                }
+            }
+
+            pub struct ContentDeserializer<'de, Err> {
+                content: Content<'de>,
+                _casper: std::marker::PhantomData<Err>,
+            }
+
+            impl<'de, Err> serde::Deserializer<'de> for ContentDeserializer<'de, Err>
+            where
+                Err: serde::de::Error,
+            {
+                type Error = Err;
+
+                fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Err>
+                where
+                    V: serde::de::Visitor<'de>,
+                {
+                    use serde::de::IntoDeserializer;
+
+                    match self.content {
+                        Content::Bool(v) => visitor.visit_bool(v),
+                        Content::U64(v) => visitor.visit_u64(v),
+                        Content::I64(v) => visitor.visit_i64(v),
+                        Content::F64(v) => visitor.visit_f64(v),
+                        Content::Char(v) => visitor.visit_char(v),
+                        Content::Str(v) => visitor.visit_borrowed_str(v),
+                        Content::String(v) => visitor.visit_string(v),
+                        Content::Bytes(v) => visitor.visit_borrowed_bytes(v),
+                        Content::ByteBuf(v) => visitor.visit_byte_buf(v),
+                        Content::None => visitor.visit_none(),
+                        Content::Some(v) => visitor.visit_some(v.into_deserializer()),
+                        Content::Unit => visitor.visit_unit(),
+                        Content::Newtype(v) => visitor.visit_newtype_struct(v.into_deserializer()),
+                        Content::Seq(v) => {
+                            serde::de::value::SeqDeserializer::new(v.into_iter()).deserialize_any(visitor)
+                        }
+                        Content::Map(v) => {
+                            serde::de::value::MapDeserializer::new(v.into_iter()).deserialize_any(visitor)
+                        }
+                    }
+                }
+
+                fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Err>
+                where
+                    V: serde::de::Visitor<'de>,
+                {
+                    use serde::de::IntoDeserializer;
+
+                    match self.content {
+                        Content::None | Content::Unit => visitor.visit_none(),
+                        Content::Some(v) => visitor.visit_some(v.into_deserializer()),
+                        _ => visitor.visit_some(self),
+                    }
+                }
+
+                fn deserialize_newtype_struct<V>(
+                    self,
+                    _name: &'static str,
+                    visitor: V,
+                ) -> Result<V::Value, Err>
+                where
+                    V: serde::de::Visitor<'de>,
+                {
+                    use serde::de::IntoDeserializer;
+
+                    match self.content {
+                        Content::Newtype(v) => visitor.visit_newtype_struct(v.into_deserializer()),
+                        _ => visitor.visit_newtype_struct(self),
+                    }
+                }
+
+                fn deserialize_enum<V>(
+                    self,
+                    _name: &'static str,
+                    _variants: &'static [&'static str],
+                    visitor: V,
+                ) -> Result<V::Value, Err>
+                where
+                    V: serde::de::Visitor<'de>,
+                {
+                    use serde::de::IntoDeserializer;
+
+                    // Unit variants are strings, the others maps with a single entry.
+                    match self.content {
+                        Content::Str(v) => {
+                            visitor.visit_enum(serde::de::value::BorrowedStrDeserializer::new(v))
+                        }
+                        Content::String(v) => visitor.visit_enum(v.into_deserializer()),
+                        Content::Map(v) if v.len() == 1 => {
+                            visitor.visit_enum(serde::de::value::MapAccessDeserializer::new(
+                                serde::de::value::MapDeserializer::new(v.into_iter()),
+                            ))
+                        }
+                        _ => self.deserialize_any(visitor),
+                    }
+                }
+
+                serde::forward_to_deserialize_any! {
+                    bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+                    bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+                    identifier ignored_any
+                }
+            }
+
+            pub struct Lenient<T>(pub core::result::Result<T, std::string::String>);
+
+            impl<'de, T> serde::Deserialize<'de> for Lenient<T>
+            where
+                T: serde::Deserialize<'de>,
+            {
+                fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+                where
+                    D: serde::Deserializer<'de>,
+                {
+                    // Binary formats generally can't be buffered, nor carry on after an
+                    // element that failed halfway through. Rather than failing as if
+                    // recovery wasn't asked for, say why it didn't happen.
+                    if !deserializer.is_human_readable() {
+                        return T::deserialize(deserializer)
+                            .map(|e| Lenient(Ok(e)))
+                            .map_err(|err| {
+                                serde::de::Error::custom(std::format_args!(
+                                    "{} (invalid elements are only replaced with defaults in human readable formats)",
+                                    err,
+                                ))
+                            });
+                    }
+
+                    let content: Content<'de> = serde::Deserialize::deserialize(deserializer)?;
+                    Ok(Lenient(
+                        T::deserialize(serde::de::IntoDeserializer::<D::Error>::into_deserializer(content))
+                            .map_err(|err| std::string::ToString::to_string(&err)),
+                    ))
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{quote, ToTokens};
+use syn::LitStr;
+
+pub fn render_serialize_fn(
+    fn_ident: &Ident,
+    len: impl ToTokens,
+    delimiter: &LitStr,
+) -> TokenStream {
+    quote! {
+        fn #fn_ident<E, S>(array: &[E; #len], serializer: S) -> core::result::Result<S::Ok, S::Error>
+        where
+            E: std::fmt::Display,
+            S: serde::Serializer,
+        {
+            use std::fmt::Write;
+
+            let mut s = std::string::String::new();
+            for (i, e) in array.iter().enumerate() {
+                if i > 0 {
+                    s.push_str(#delimiter);
+                }
+                std::write!(s, "{}", e).map_err(serde::ser::Error::custom)?;
+            }
+            serializer.serialize_str(&s)
+        }
+    }
+}
+
+pub fn render_deserialize_fn(
+    fn_ident: &Ident,
+    len: impl ToTokens,
+    delimiter: &LitStr,
+) -> TokenStream {
+    quote! {
+        fn #fn_ident<'de, E, D>(deserializer: D) -> core::result::Result<[E; #len], D::Error>
+        where
+            E: std::str::FromStr,
+            <E as std::str::FromStr>::Err: std::fmt::Display,
+            D: serde::Deserializer<'de>,
+        {
+            use core::convert::TryInto;
+
+            struct DelimitedVisitor<E> {
+                _casper: std::marker::PhantomData<E>,
+            }
+
+            impl<'de, E> serde::de::Visitor<'de> for DelimitedVisitor<E>
+            where
+                E: std::str::FromStr,
+                <E as std::str::FromStr>::Err: std::fmt::Display,
+            {
+                type Value = [E; #len];
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    std::write!(formatter, "a string of {} elements separated by {:?}", #len, #delimiter)
+                }
+
+                fn visit_str<Err>(self, v: &str) -> Result<Self::Value, Err>
+                where
+                    Err: serde::de::Error,
+                {
+                    let mut elems = std::vec::Vec::with_capacity(#len);
+
+                    for (i, token) in v.split(#delimiter).enumerate() {
+                        if i == #len {
+                            return Err(serde::de::Error::invalid_length(v.split(#delimiter).count(), &self));
+                        }
+
+                        let token = token.trim();
+                        match token.parse() {
+                            Ok(e) => elems.push(e),
+                            Err(err) => {
+                                return Err(serde::de::Error::custom(std::format_args!(
+                                    "invalid element at index {} ({:?}): {}",
+                                    i,
+                                    token,
+                                    err,
+                                )))
+                            }
+                        }
+                    }
+
+                    match elems.try_into() {
+                        Ok(arr) => Ok(arr),
+                        Err(elems) => Err(serde::de::Error::invalid_length(elems.len(), &self)),
+                    }
+                }
+            }
+
+            deserializer.deserialize_str(DelimitedVisitor {
+                _casper: std::marker::PhantomData,
+            })
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{quote, ToTokens};
+use syn::{Type, TypePath};
+
+/// A primitive integer type that can be delta encoded.
+pub struct IntType {
+    ty: Ident,
+    signed: bool,
+    /// The signed type of the same width. Zigzag encoded deltas are computed in it.
+    signed_ty: Ident,
+    /// The unsigned type of the same width. Zigzag encoded deltas are stored as it.
+    unsigned_ty: Ident,
+}
+
+impl IntType {
+    pub fn parse(ty: &Type) -> Option<Self> {
+        let ident = match ty {
+            Type::Path(TypePath { qself: None, path }) => path.get_ident()?,
+            _ => return None,
+        };
+
+        let (signed_ty, unsigned_ty) = match ident.to_string().as_str() {
+            "u8" | "i8" => ("i8", "u8"),
+            "u16" | "i16" => ("i16", "u16"),
+            "u32" | "i32" => ("i32", "u32"),
+            "u64" | "i64" => ("i64", "u64"),
+            "u128" | "i128" => ("i128", "u128"),
+            "usize" | "isize" => ("isize", "usize"),
+            _ => return None,
+        };
+
+        Some(IntType {
+            ty: ident.clone(),
+            signed: *ident == signed_ty,
+            signed_ty: Ident::new(signed_ty, Span::call_site()),
+            unsigned_ty: Ident::new(unsigned_ty, Span::call_site()),
+        })
+    }
+}
+
+/// Deltas of signed types are always zigzag encoded. Those of unsigned types only are
+/// if `zigzag` is set, since that makes the deltas of increasing arrays twice as big.
+pub fn render_serialize_fn(
+    fn_ident: &Ident,
+    len: impl ToTokens,
+    int: IntType,
+    zigzag: bool,
+) -> TokenStream {
+    let IntType {
+        ty,
+        signed,
+        signed_ty,
+        unsigned_ty,
+    } = &int;
+    let overflow = quote! {
+        serde::ser::Error::custom(std::format_args!(
+            "difference between elements {} and {} overflows {}",
+            i - 1,
+            i,
+            std::stringify!(#signed_ty),
+        ))
+    };
+
+    let encode_delta = if *signed {
+        quote! {
+            let delta = e.checked_sub(array[i - 1]).ok_or_else(|| #overflow)?;
+        }
+    } else if zigzag {
+        quote! {
+            let delta = e.wrapping_sub(array[i - 1]) as #signed_ty;
+            if array[i - 1].checked_add_signed(delta) != Some(*e) {
+                return Err(#overflow);
+            }
+        }
+    } else {
+        quote! {
+            let delta = e.checked_sub(array[i - 1]).ok_or_else(|| {
+                serde::ser::Error::custom(std::format_args!(
+                    "delta encoding requires a non-decreasing array, but element {} is smaller than element {}",
+                    i,
+                    i - 1,
+                ))
+            })?;
+        }
+    };
+    let encode_delta = if *signed || zigzag {
+        quote! {
+            #encode_delta
+            let delta = ((delta << 1) ^ (delta >> (core::mem::size_of::<#signed_ty>() * 8 - 1))) as #unsigned_ty;
+        }
+    } else {
+        encode_delta
+    };
+
+    quote! {
+        fn #fn_ident<S>(array: &[#ty; #len], serializer: S) -> core::result::Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            use serde::ser::SerializeTuple;
+
+            let mut seq = serializer.serialize_tuple(#len)?;
+            for (i, e) in array.iter().enumerate() {
+                if i == 0 {
+                    seq.serialize_element(e)?;
+                } else {
+                    #encode_delta
+                    seq.serialize_element(&delta)?;
+                }
+            }
+            seq.end()
+        }
+    }
+}
+
+pub fn render_deserialize_fn(
+    fn_ident: &Ident,
+    len: impl ToTokens,
+    int: IntType,
+    zigzag: bool,
+) -> TokenStream {
+    let IntType {
+        ty,
+        signed,
+        signed_ty,
+        unsigned_ty,
+    } = &int;
+
+    let (delta_ty, decode_delta, add) = if *signed || zigzag {
+        let add = if *signed {
+            quote! { checked_add }
+        } else {
+            quote! { checked_add_signed }
+        };
+        (
+            unsigned_ty,
+            quote! {
+                let delta = ((delta >> 1) as #signed_ty) ^ -((delta & 1) as #signed_ty);
+            },
+            add,
+        )
+    } else {
+        (ty, quote! {}, quote! { checked_add })
+    };
+
+    quote! {
+        fn #fn_ident<'de, D>(deserializer: D) -> core::result::Result<[#ty; #len], D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            struct ArrayVisitor;
+
+            impl<'de> serde::de::Visitor<'de> for ArrayVisitor {
+                type Value = [#ty; #len];
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    formatter.write_str(std::concat!("a delta encoded array"))
+                }
+
+                #[inline]
+                fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+                where
+                    A: serde::de::SeqAccess<'de>,
+                {
+                    let mut arr: [#ty; #len] = [0; #len];
+
+                    for i in 0..arr.len() {
+                        if i == 0 {
+                            arr[i] = match seq.next_element()? {
+                                Some(val) => val,
+                                None => return Err(serde::de::Error::invalid_length(i, &self)),
+                            };
+                        } else {
+                            let delta: #delta_ty = match seq.next_element()? {
+                                Some(val) => val,
+                                None => return Err(serde::de::Error::invalid_length(i, &self)),
+                            };
+                            #decode_delta
+                            arr[i] = arr[i - 1].#add(delta).ok_or_else(|| {
+                                serde::de::Error::custom(std::format_args!(
+                                    "element {} of a delta encoded array overflows {}",
+                                    i,
+                                    std::stringify!(#ty),
+                                ))
+                            })?;
+                        }
+                    }
+
+                    Ok(arr)
+                }
+            }
+
+            deserializer.deserialize_tuple(#len, ArrayVisitor)
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{quote, ToTokens};
+use syn::{Type, TypePath};
+
+/// Byte order of the elements of a packed array.
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+impl Endianness {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "le" => Some(Endianness::Little),
+            "be" => Some(Endianness::Big),
+            _ => None,
+        }
+    }
+
+    fn conversions(&self) -> (Ident, Ident) {
+        match self {
+            Endianness::Little => (
+                Ident::new("to_le_bytes", Span::call_site()),
+                Ident::new("from_le_bytes", Span::call_site()),
+            ),
+            Endianness::Big => (
+                Ident::new("to_be_bytes", Span::call_site()),
+                Ident::new("from_be_bytes", Span::call_site()),
+            ),
+        }
+    }
+}
+
+/// A fixed-width numeric type that can be packed into bytes.
+pub struct NumType {
+    ty: Ident,
+}
+
+impl NumType {
+    pub fn parse(ty: &Type) -> Option<Self> {
+        let ident = match ty {
+            Type::Path(TypePath { qself: None, path }) => path.get_ident()?,
+            _ => return None,
+        };
+
+        match ident.to_string().as_str() {
+            "u8" | "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32" | "i64" | "i128"
+            | "f32" | "f64" => Some(NumType { ty: ident.clone() }),
+            _ => None,
+        }
+    }
+}
+
+pub fn render_serialize_fn(
+    fn_ident: &Ident,
+    len: impl ToTokens,
+    num: NumType,
+    endianness: &Endianness,
+) -> TokenStream {
+    let ty = &num.ty;
+    let (to_bytes, _) = endianness.conversions();
+
+    quote! {
+        fn #fn_ident<S>(array: &[#ty; #len], serializer: S) -> core::result::Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            let mut bytes = std::vec::Vec::with_capacity(#len * core::mem::size_of::<#ty>());
+            for e in array {
+                bytes.extend_from_slice(&e.#to_bytes());
+            }
+            serializer.serialize_bytes(&bytes)
+        }
+    }
+}
+
+pub fn render_deserialize_fn(
+    fn_ident: &Ident,
+    len: impl ToTokens,
+    num: NumType,
+    endianness: &Endianness,
+) -> TokenStream {
+    let ty = &num.ty;
+    let (_, from_bytes) = endianness.conversions();
+
+    quote! {
+        fn #fn_ident<'de, D>(deserializer: D) -> core::result::Result<[#ty; #len], D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            struct BytesVisitor;
+
+            impl BytesVisitor {
+                const SIZE: usize = core::mem::size_of::<#ty>();
+            }
+
+            impl<'de> serde::de::Visitor<'de> for BytesVisitor {
+                type Value = [#ty; #len];
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    std::write!(formatter, "{} bytes", #len * Self::SIZE)
+                }
+
+                fn visit_bytes<Err>(self, v: &[u8]) -> Result<Self::Value, Err>
+                where
+                    Err: serde::de::Error,
+                {
+                    if v.len() != #len * Self::SIZE {
+                        return Err(serde::de::Error::invalid_length(v.len(), &self));
+                    }
+
+                    let mut arr: [#ty; #len] = [<#ty as core::default::Default>::default(); #len];
+                    for (e, chunk) in arr.iter_mut().zip(v.chunks_exact(Self::SIZE)) {
+                        let mut bytes = [0; core::mem::size_of::<#ty>()];
+                        bytes.copy_from_slice(chunk);
+                        *e = <#ty>::#from_bytes(bytes);
+                    }
+
+                    Ok(arr)
+                }
+
+                // Formats without a native byte string type (like JSON) represent bytes
+                // as a sequence.
+                fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+                where
+                    A: serde::de::SeqAccess<'de>,
+                {
+                    let mut bytes = std::vec::Vec::with_capacity(#len * Self::SIZE);
+                    while let Some(byte) = seq.next_element::<u8>()? {
+                        bytes.push(byte);
+                        if bytes.len() > #len * Self::SIZE {
+                            return Err(serde::de::Error::invalid_length(bytes.len(), &self));
+                        }
+                    }
+                    self.visit_bytes(&bytes)
+                }
+            }
+
+            deserializer.deserialize_bytes(BytesVisitor)
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote, ToTokens};
+
+use super::tuple;
+
+pub fn render_serialize_fn(
+    fn_ident: &Ident,
+    len: impl ToTokens,
+    row_len: &TokenStream,
+    in_binary: bool,
+) -> TokenStream {
+    let tuple_fn_ident = format_ident!("{}_tuple", fn_ident);
+    let tuple_fn = tuple::render_serialize_fn(&tuple_fn_ident, &len, Default::default());
+
+    quote! {
+        fn #fn_ident<E, S>(array: &[E; #len], serializer: S) -> core::result::Result<S::Ok, S::Error>
+        where
+            E: serde::Serialize,
+            S: serde::Serializer,
+        {
+            #tuple_fn
+
+            if !#in_binary && !serializer.is_human_readable() {
+                return #tuple_fn_ident(array, serializer);
+            }
+
+            use serde::ser::SerializeSeq;
+
+            let rows = array.chunks(#row_len);
+            let mut seq = serializer.serialize_seq(Some(rows.len()))?;
+            for row in rows {
+                seq.serialize_element(row)?;
+            }
+            seq.end()
+        }
+    }
+}
+
+pub fn render_deserialize_fn(
+    fn_ident: &Ident,
+    len: impl ToTokens,
+    row_len: &TokenStream,
+    in_binary: bool,
+    safe: bool,
+) -> TokenStream {
+    let tuple_fn_ident = format_ident!("{}_tuple", fn_ident);
+    let tuple_fn = tuple::render_deserialize_fn(
+        &tuple_fn_ident,
+        &len,
+        tuple::Element {
+            safe,
+            ..Default::default()
+        },
+    );
+
+    quote! {
+        fn #fn_ident<'de, E, D>(deserializer: D) -> core::result::Result<[E; #len], D::Error>
+        where
+            E: serde::Deserialize<'de>,
+            D: serde::Deserializer<'de>,
+        {
+            use core::convert::TryInto;
+
+            #tuple_fn
+
+            if !#in_binary && !deserializer.is_human_readable() {
+                return #tuple_fn_ident(deserializer);
+            }
+
+            struct RowsVisitor<E> {
+                _casper: std::marker::PhantomData<E>,
+            }
+
+            impl<'de, E> serde::de::Visitor<'de> for RowsVisitor<E>
+            where
+                E: serde::Deserialize<'de>,
+            {
+                type Value = [E; #len];
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    std::write!(
+                        formatter,
+                        "{} elements in rows of {}",
+                        #len,
+                        #row_len,
+                    )
+                }
+
+                fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+                where
+                    A: serde::de::SeqAccess<'de>,
+                {
+                    let mut elems = std::vec::Vec::with_capacity(#len);
+                    let mut rows = 0;
+
+                    while elems.len() < #len {
+                        let row = RowSeed {
+                            elems: &mut elems,
+                            row: rows,
+                        };
+                        if seq.next_element_seed(row)?.is_none() {
+                            return Err(serde::de::Error::invalid_length(rows, &self));
+                        }
+                        rows += 1;
+                    }
+
+                    if seq.next_element::<serde::de::IgnoredAny>()?.is_some() {
+                        return Err(serde::de::Error::invalid_length(rows + 1, &self));
+                    }
+
+                    Ok(match elems.try_into() {
+                        Ok(arr) => arr,
+                        Err(_) => unreachable!(),
+                    })
+                }
+            }
+
+            /// Deserializes a single row, appending its elements to `elems`.
+            struct RowSeed<'a, E> {
+                elems: &'a mut std::vec::Vec<E>,
+                row: usize,
+            }
+
+            impl<'a, E> RowSeed<'a, E> {
+                fn expected_len(&self) -> usize {
+                    core::cmp::min(#row_len, #len - self.row * #row_len)
+                }
+            }
+
+            impl<'a, 'de, E> serde::de::DeserializeSeed<'de> for RowSeed<'a, E>
+            where
+                E: serde::Deserialize<'de>,
+            {
+                type Value = ();
+
+                fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+                where
+                    D: serde::Deserializer<'de>,
+                {
+                    deserializer.deserialize_seq(self)
+                }
+            }
+
+            impl<'a, 'de, E> serde::de::Visitor<'de> for RowSeed<'a, E>
+            where
+                E: serde::Deserialize<'de>,
+            {
+                type Value = ();
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    std::write!(formatter, "row {} of {} elements", self.row, self.expected_len())
+                }
+
+                fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+                where
+                    A: serde::de::SeqAccess<'de>,
+                {
+                    let expected_len = self.expected_len();
+
+                    for i in 0..expected_len {
+                        match seq.next_element()? {
+                            Some(val) => self.elems.push(val),
+                            None => return Err(serde::de::Error::invalid_length(i, &self)),
+                        }
+                    }
+
+                    if seq.next_element::<serde::de::IgnoredAny>()?.is_some() {
+                        return Err(serde::de::Error::invalid_length(expected_len + 1, &self));
+                    }
+
+                    Ok(())
+                }
+            }
+
+            deserializer.deserialize_seq(RowsVisitor {
+                _casper: std::marker::PhantomData,
+            })
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{quote, ToTokens};
+use syn::{Type, TypePath};
+
+/// Checks if the type is a byte type a text buffer can be made of.
+pub fn is_byte_type(ty: &Type) -> bool {
+    if let Type::Path(TypePath { qself: None, path }) = ty {
+        if let Some(last_segment) = path.segments.iter().last() {
+            return last_segment.arguments.is_empty()
+                && ["u8", "i8", "c_char"]
+                    .iter()
+                    .any(|byte_ty| last_segment.ident == byte_ty);
+        }
+    }
+
+    false
+}
+
+pub fn render_serialize_fn(
+    fn_ident: &Ident,
+    len: impl ToTokens,
+    ty: &Type,
+    nul_terminated: bool,
+) -> TokenStream {
+    let check_terminated = if nul_terminated {
+        quote! {
+            if end == #len {
+                return Err(serde::ser::Error::custom(std::format_args!(
+                    "C string in a buffer of capacity {} is not NUL-terminated",
+                    #len,
+                )));
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        fn #fn_ident<S>(array: &[#ty; #len], serializer: S) -> core::result::Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            let end = array.iter().position(|&c| c == 0).unwrap_or(#len);
+            #check_terminated
+
+            let bytes: std::vec::Vec<u8> = array[..end].iter().map(|&c| c as u8).collect();
+            let s = core::str::from_utf8(&bytes).map_err(|e| {
+                serde::ser::Error::custom(std::format_args!(
+                    "invalid UTF-8 in a text buffer of capacity {}: {}",
+                    #len,
+                    e,
+                ))
+            })?;
+            serializer.serialize_str(s)
+        }
+    }
+}
+
+pub fn render_deserialize_fn(
+    fn_ident: &Ident,
+    len: impl ToTokens,
+    ty: &Type,
+    nul_terminated: bool,
+) -> TokenStream {
+    // A C string needs room for the terminating NUL.
+    let max_len = if nul_terminated {
+        quote! { #len - 1 }
+    } else {
+        quote! { #len }
+    };
+
+    quote! {
+        fn #fn_ident<'de, D>(deserializer: D) -> core::result::Result<[#ty; #len], D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            struct TextVisitor;
+
+            impl<'de> serde::de::Visitor<'de> for TextVisitor {
+                type Value = [#ty; #len];
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    std::write!(formatter, "a string of at most {} bytes", #max_len)
+                }
+
+                fn visit_str<Err>(self, v: &str) -> Result<Self::Value, Err>
+                where
+                    Err: serde::de::Error,
+                {
+                    if v.len() > #max_len {
+                        return Err(serde::de::Error::custom(std::format_args!(
+                            "string of {} bytes doesn't fit in a text buffer of capacity {}",
+                            v.len(),
+                            #len,
+                        )));
+                    }
+                    if v.contains('\0') {
+                        return Err(serde::de::Error::custom(std::format_args!(
+                            "string stored in a text buffer of capacity {} can't contain NUL",
+                            #len,
+                        )));
+                    }
+
+                    let mut arr: [#ty; #len] = [0; #len];
+                    for (c, b) in arr.iter_mut().zip(v.bytes()) {
+                        *c = b as #ty;
+                    }
+
+                    Ok(arr)
+                }
+
+                fn visit_bytes<Err>(self, v: &[u8]) -> Result<Self::Value, Err>
+                where
+                    Err: serde::de::Error,
+                {
+                    let s = core::str::from_utf8(v).map_err(|e| {
+                        serde::de::Error::custom(std::format_args!(
+                            "invalid UTF-8 for a text buffer of capacity {}: {}",
+                            #len,
+                            e,
+                        ))
+                    })?;
+                    self.visit_str(s)
+                }
+            }
+
+            deserializer.deserialize_str(TextVisitor)
+        }
+    }
+}
@@ -0,0 +1,614 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{quote, ToTokens};
+use syn::{ExprPath, GenericArgument, PathArguments, Type, TypeArray, TypePath, TypeReference};
+
+/// A pointer an array is stored behind, as in `Box<[T; N]>` or `&'a [T; N]`.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Pointer {
+    Ref,
+    Box,
+    Rc,
+    Arc,
+}
+
+impl Pointer {
+    /// Splits a type like `Box<[T; N]>` into the pointer and the array type.
+    pub fn parse(ty: &Type) -> Option<(Self, &TypeArray)> {
+        if let Type::Reference(TypeReference {
+            mutability: None,
+            elem,
+            ..
+        }) = ty
+        {
+            if let Type::Array(array_type) = &**elem {
+                return Some((Pointer::Ref, array_type));
+            }
+        } else if let Type::Path(TypePath { qself: None, path }) = ty {
+            let last_segment = path.segments.iter().last()?;
+            let pointer = match last_segment.ident.to_string().as_str() {
+                "Box" => Pointer::Box,
+                "Rc" => Pointer::Rc,
+                "Arc" => Pointer::Arc,
+                _ => return None,
+            };
+
+            if let PathArguments::AngleBracketed(args) = &last_segment.arguments {
+                if let [GenericArgument::Type(Type::Array(array_type))] =
+                    args.args.iter().collect::<Vec<_>>().as_slice()
+                {
+                    return Some((pointer, array_type));
+                }
+            }
+        }
+
+        None
+    }
+
+    fn path(&self) -> TokenStream {
+        match self {
+            Pointer::Ref => unreachable!(),
+            Pointer::Box => quote! { std::boxed::Box },
+            Pointer::Rc => quote! { std::rc::Rc },
+            Pointer::Arc => quote! { std::sync::Arc },
+        }
+    }
+}
+
+/// What a `Cow` element borrows from the input.
+enum CowKind {
+    Str,
+    Bytes,
+}
+
+impl CowKind {
+    /// Finds out what a `Cow<'a, str>` or `Cow<'a, [u8]>` type borrows.
+    fn parse(ty: &Type) -> Option<Self> {
+        if let Type::Path(TypePath { qself: None, path }) = ty {
+            let last_segment = path.segments.iter().last()?;
+            if last_segment.ident != "Cow" {
+                return None;
+            }
+
+            if let PathArguments::AngleBracketed(args) = &last_segment.arguments {
+                if let [GenericArgument::Lifetime(_), GenericArgument::Type(borrowed)] =
+                    args.args.iter().collect::<Vec<_>>().as_slice()
+                {
+                    match borrowed {
+                        Type::Path(TypePath { qself: None, path }) if path.is_ident("str") => {
+                            return Some(CowKind::Str)
+                        }
+                        Type::Slice(slice) if super::borrowed::is_u8(&slice.elem) => {
+                            return Some(CowKind::Bytes)
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Renders a function deserializing a `Cow` that borrows when the format allows.
+    fn render_deserialize_fn(
+        &self,
+        fn_ident: &Ident,
+        e_param: &TokenStream,
+        e_bound: &TokenStream,
+        elem: &TokenStream,
+    ) -> TokenStream {
+        let (value, deserialize, visit) = match self {
+            CowKind::Str => (
+                quote! { std::borrow::Cow<'de, str> },
+                quote! { deserialize_str },
+                quote! {
+                    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        formatter.write_str("a string")
+                    }
+
+                    fn visit_borrowed_str<Err>(self, v: &'de str) -> Result<Self::Value, Err> {
+                        Ok(std::borrow::Cow::Borrowed(v))
+                    }
+
+                    fn visit_str<Err>(self, v: &str) -> Result<Self::Value, Err> {
+                        Ok(std::borrow::Cow::Owned(std::borrow::ToOwned::to_owned(v)))
+                    }
+
+                    fn visit_string<Err>(self, v: std::string::String) -> Result<Self::Value, Err> {
+                        Ok(std::borrow::Cow::Owned(v))
+                    }
+                },
+            ),
+            CowKind::Bytes => (
+                quote! { std::borrow::Cow<'de, [u8]> },
+                quote! { deserialize_bytes },
+                quote! {
+                    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        formatter.write_str("bytes")
+                    }
+
+                    fn visit_borrowed_bytes<Err>(self, v: &'de [u8]) -> Result<Self::Value, Err> {
+                        Ok(std::borrow::Cow::Borrowed(v))
+                    }
+
+                    fn visit_borrowed_str<Err>(self, v: &'de str) -> Result<Self::Value, Err> {
+                        Ok(std::borrow::Cow::Borrowed(v.as_bytes()))
+                    }
+
+                    fn visit_bytes<Err>(self, v: &[u8]) -> Result<Self::Value, Err> {
+                        Ok(std::borrow::Cow::Owned(v.to_vec()))
+                    }
+
+                    fn visit_str<Err>(self, v: &str) -> Result<Self::Value, Err> {
+                        Ok(std::borrow::Cow::Owned(v.as_bytes().to_vec()))
+                    }
+
+                    fn visit_byte_buf<Err>(self, v: std::vec::Vec<u8>) -> Result<Self::Value, Err> {
+                        Ok(std::borrow::Cow::Owned(v))
+                    }
+
+                    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+                    where
+                        A: serde::de::SeqAccess<'de>,
+                    {
+                        let mut bytes = std::vec::Vec::new();
+                        while let Some(b) = seq.next_element()? {
+                            bytes.push(b);
+                        }
+                        Ok(std::borrow::Cow::Owned(bytes))
+                    }
+                },
+            ),
+        };
+
+        quote! {
+            /// Borrows the element from the input if the format allows.
+            fn #fn_ident<'de, #e_param D>(deserializer: D) -> core::result::Result<#elem, D::Error>
+            where
+                #e_bound
+                D: serde::Deserializer<'de>,
+            {
+                struct CowVisitor;
+
+                impl<'de> serde::de::Visitor<'de> for CowVisitor {
+                    type Value = #value;
+
+                    #visit
+                }
+
+                deserializer.#deserialize(CowVisitor)
+            }
+        }
+    }
+}
+
+/// Checks if elements of the type can be borrowed from the input by a custom
+/// deserializer, instead of always being owned.
+pub fn is_borrowable_cow(ty: &Type) -> bool {
+    CowKind::parse(ty).is_some()
+}
+
+/// Element-level customizations of a tuple encoded array.
+#[derive(Default)]
+pub struct Element<'a> {
+    /// The element type. If this is set, the generated code isn't generic over
+    /// the element type. This is needed for the other options, since custom
+    /// functions are only known to work with one type.
+    pub ty: Option<&'a Type>,
+    /// A custom (de)serializer for individual elements, much like Serde's
+    /// `serialize_with`/`deserialize_with`.
+    pub with: Option<&'a ExprPath>,
+    /// A function validating each element as soon as it's deserialized.
+    pub validate: Option<&'a ExprPath>,
+    /// Replace elements that fail to deserialize with `Default::default()`, using the
+    /// `Lenient` wrapper in the given module.
+    pub default_on_error: Option<&'a Ident>,
+    /// A function to call with the index and the error of each element replaced
+    /// with the default.
+    pub error_hook: Option<&'a ExprPath>,
+    /// Generate code without any `unsafe`. Elements are collected in a `Vec` first.
+    pub safe: bool,
+    /// Wipe the buffer the array is deserialized into, whether deserialization
+    /// fails or not. Not available in safe mode.
+    pub zeroize: bool,
+    /// The smart pointer the array is stored in, if any. Such arrays are
+    /// deserialized straight into their heap allocation.
+    pub pointer: Option<Pointer>,
+    /// Whether the elements borrow from the input, as with `#[serde(borrow)]`.
+    pub borrow: bool,
+}
+
+impl<'a> Element<'a> {
+    /// Returns the type of the whole field, given the element type.
+    fn container(&self, elem: &TokenStream, len: impl ToTokens) -> TokenStream {
+        match self.pointer {
+            Some(Pointer::Ref) => quote! { &[#elem; #len] },
+            Some(pointer) => {
+                let path = pointer.path();
+                quote! { #path<[#elem; #len]> }
+            }
+            None => quote! { [#elem; #len] },
+        }
+    }
+
+    /// Returns the tokens for generic parameters, their bounds and the element type
+    /// to use in a generated function. A concrete element type's lifetimes become
+    /// the parameters, outlived by `'de` when deserializing borrowed elements.
+    fn generics(&self, bound: TokenStream, de: bool) -> (TokenStream, TokenStream, TokenStream) {
+        match self.ty {
+            Some(ty) => {
+                let lifetimes = super::lifetimes(ty);
+                let outlives = if de && self.borrow {
+                    quote! { #('de: #lifetimes,)* }
+                } else {
+                    quote! {}
+                };
+
+                (quote! { #(#lifetimes,)* }, outlives, ty.into_token_stream())
+            }
+            None => (quote! { E, }, quote! { E: #bound, }, quote! { E }),
+        }
+    }
+}
+
+pub fn render_serialize_fn(fn_ident: &Ident, len: impl ToTokens, element: Element) -> TokenStream {
+    let (e_param, e_bound, elem) = element.generics(quote! { serde::Serialize }, false);
+    let container = element.container(&elem, &len);
+    let (wrapper, wrap_e) = match element.with {
+        Some(path) => (
+            quote! {
+                struct SerializeWith<'serbia, #e_param>(&'serbia #elem);
+
+                impl<'serbia, #e_param> serde::Serialize for SerializeWith<'serbia, #e_param> {
+                    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+                    where
+                        S: serde::Serializer,
+                    {
+                        #path(self.0, serializer)
+                    }
+                }
+            },
+            quote! { &SerializeWith(e) },
+        ),
+        None => (quote! {}, quote! { e }),
+    };
+
+    quote! {
+        fn #fn_ident<#e_param S>(array: &#container, serializer: S) -> core::result::Result<S::Ok, S::Error>
+        where
+            #e_bound
+            S: serde::Serializer,
+        {
+            use serde::ser::SerializeTuple;
+
+            #wrapper
+
+            let mut seq = serializer.serialize_tuple(#len)?;
+            for e in array.iter() {
+                seq.serialize_element(#wrap_e)?;
+            }
+            seq.end()
+        }
+    }
+}
+
+pub fn render_deserialize_fn(
+    fn_ident: &Ident,
+    len: impl ToTokens,
+    element: Element,
+) -> TokenStream {
+    let mut bound = quote! { serde::Deserialize<'de> };
+    if element.default_on_error.is_some() {
+        bound = quote! { #bound + core::default::Default };
+    }
+    if element.zeroize {
+        bound = quote! { #bound + zeroize::Zeroize };
+    }
+    let (e_param, e_bound, elem) = element.generics(bound, true);
+    let container = element.container(&elem, &len);
+
+    // Borrowed `Cow` elements need a deserializer of their own, since their
+    // `Deserialize` implementation always makes an owned copy.
+    let borrow_cow = element
+        .ty
+        .filter(|_| element.borrow && element.with.is_none())
+        .and_then(CowKind::parse)
+        .map(|kind| {
+            let fn_ident = Ident::new("borrow_cow", proc_macro2::Span::call_site());
+            let path: ExprPath = syn::parse_quote!(borrow_cow);
+            (
+                kind.render_deserialize_fn(&fn_ident, &e_param, &e_bound, &elem),
+                path,
+            )
+        });
+    let (borrow_cow_fn, with) = match &borrow_cow {
+        Some((borrow_cow_fn, path)) => (borrow_cow_fn.clone(), Some(path)),
+        None => (quote! {}, element.with),
+    };
+
+    let (with_wrapper, read_ty, from_read) = match with {
+        Some(path) => (
+            quote! {
+                struct DeserializeWith<#e_param>(#elem);
+
+                impl<'de, #e_param> serde::Deserialize<'de> for DeserializeWith<#e_param>
+                where
+                    #e_bound
+                {
+                    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+                    where
+                        D: serde::Deserializer<'de>,
+                    {
+                        #path(deserializer).map(DeserializeWith)
+                    }
+                }
+            },
+            quote! { DeserializeWith },
+            quote! { e.0 },
+        ),
+        None => (quote! {}, elem.clone(), quote! { e }),
+    };
+    let next_element = if let Some(lenient_mod) = element.default_on_error {
+        let call_hook = element.error_hook.map(|path| quote! { #path(i, &err); });
+
+        quote! {
+            match seq.next_element_seed(Indexed::<#lenient_mod::Lenient<#read_ty>>::new(i))? {
+                Some(#lenient_mod::Lenient(Ok(e))) => Some(#from_read),
+                Some(#lenient_mod::Lenient(Err(err))) => {
+                    #call_hook
+                    Some(<#elem as core::default::Default>::default())
+                }
+                None => None,
+            }
+        }
+    } else {
+        match with {
+            Some(_) => quote! {
+                seq.next_element_seed(Indexed::<#read_ty>::new(i))?.map(|e| #from_read)
+            },
+            None => quote! {
+                seq.next_element_seed(Indexed::<#read_ty>::new(i))?
+            },
+        }
+    };
+
+    // Elements are validated once stored, so that invalid ones get cleaned up
+    // along with the rest.
+    let validate = |stored: TokenStream| {
+        element.validate.map(|path| {
+            quote! {
+                let val = #stored;
+                if let Err(err) = #path(i, val) {
+                    return Err(serde::de::Error::custom(std::format_args!(
+                        "invalid element at index {}: {}",
+                        i,
+                        err,
+                    )));
+                }
+            }
+        })
+    };
+    let read_element = quote! {
+        let val = match #next_element {
+            Some(val) => val,
+            None => return Err(serde::de::Error::invalid_length(i, self)),
+        };
+    };
+
+    // The elements are collected on the heap, with a `Vec` taking care of dropping
+    // them by itself. The array then only ever exists on the stack as the return value.
+    // Unboxing happens in the caller, so that no frame the elements are deserialized
+    // in has room for the whole array.
+    let validate_on_heap = validate(quote! { &arr[i] });
+    let visit_seq_on_heap = quote! {
+        #[inline(never)]
+        fn visit_seq_on_heap<A>(
+            &self,
+            mut seq: A,
+        ) -> Result<std::boxed::Box<[#elem; #len]>, A::Error>
+        where
+            A: serde::de::SeqAccess<'de>,
+        {
+            let mut arr = std::vec::Vec::with_capacity(#len);
+
+            for i in 0..#len {
+                #read_element
+                arr.push(val);
+                #validate_on_heap
+            }
+
+            match core::convert::TryInto::try_into(arr.into_boxed_slice()) {
+                Ok(arr) => Ok(arr),
+                Err(_) => unreachable!(),
+            }
+        }
+    };
+
+    // The elements are written to an uninitialized array on the stack, which saves
+    // an allocation. A guard drops the initialized elements on every early return
+    // or panic.
+    // Elements are wiped before they're dropped, which also covers whatever they own.
+    // What's left of the buffer is wiped byte by byte afterwards, along with the
+    // copies in it of the elements moved out into the finished array.
+    let (guard_bound, wipe_elem, wipe_guard, wipe_arr) = if element.zeroize {
+        (
+            quote! { where T: zeroize::Zeroize },
+            quote! { zeroize::Zeroize::zeroize(unsafe { &mut *elem.as_mut_ptr() }); },
+            quote! { zeroize::Zeroize::zeroize(&mut *self.arr); },
+            quote! { zeroize::Zeroize::zeroize(&mut arr[..]); },
+        )
+    } else {
+        (quote! {}, quote! {}, quote! {}, quote! {})
+    };
+    let validate_on_stack = validate(quote! { unsafe { &*guard.arr[i].as_ptr() } });
+    let visit_seq_on_stack = quote! {
+        #[inline(never)]
+        fn visit_seq_on_stack<A>(&self, mut seq: A) -> Result<[#elem; #len], A::Error>
+        where
+            A: serde::de::SeqAccess<'de>,
+        {
+            use std::mem::{self, MaybeUninit};
+
+            /// Drops the initialized prefix of the array unless forgotten.
+            struct Guard<'a, T> #guard_bound {
+                arr: &'a mut [MaybeUninit<T>],
+                initialized: usize,
+            }
+
+            impl<'a, T> Drop for Guard<'a, T> #guard_bound {
+                fn drop(&mut self) {
+                    for elem in &mut self.arr[..self.initialized] {
+                        #wipe_elem
+                        // TODO This would be better with assume_init_drop nightly function
+                        // https://github.com/rust-lang/rust/issues/63567
+                        unsafe { std::ptr::drop_in_place(elem.as_mut_ptr()) };
+                    }
+                    #wipe_guard
+                }
+            }
+
+            let mut arr: [MaybeUninit<#elem>; #len] = unsafe { MaybeUninit::uninit().assume_init() };
+            let mut guard = Guard {
+                arr: &mut arr,
+                initialized: 0,
+            };
+
+            for i in 0..#len {
+                #read_element
+                guard.arr[i] = MaybeUninit::new(val);
+                guard.initialized += 1;
+                #validate_on_stack
+            }
+
+            mem::forget(guard);
+            let array = unsafe { mem::transmute_copy::<_, [#elem; #len]>(&arr) };
+            #wipe_arr
+            Ok(array)
+        }
+    };
+
+    // Arrays in smart pointers are moved from the heap allocation they're collected
+    // in to their final one, without going through the stack. Safe mode only has the
+    // heap path. Wiping only works on the stack, where the whole buffer is under our
+    // control. Otherwise big arrays go through the heap, since the stack path holds
+    // two copies of the array at the end.
+    let (visit_seq_paths, visit_seq) = if let Some(pointer) = element.pointer {
+        let visit_seq = match pointer {
+            Pointer::Box => quote! { self.visit_seq_on_heap(seq) },
+            _ => {
+                let path = pointer.path();
+                quote! { self.visit_seq_on_heap(seq).map(#path::from) }
+            }
+        };
+        (visit_seq_on_heap, visit_seq)
+    } else if element.safe {
+        (
+            visit_seq_on_heap,
+            quote! { self.visit_seq_on_heap(seq).map(|arr| *arr) },
+        )
+    } else if element.zeroize {
+        (visit_seq_on_stack, quote! { self.visit_seq_on_stack(seq) })
+    } else {
+        (
+            quote! {
+                #visit_seq_on_heap
+                #visit_seq_on_stack
+            },
+            quote! {
+                if std::mem::size_of::<[#elem; #len]>() > 16 * 1024 {
+                    self.visit_seq_on_heap(seq).map(|arr| *arr)
+                } else {
+                    self.visit_seq_on_stack(seq)
+                }
+            },
+        )
+    };
+
+    quote! {
+        fn #fn_ident<'de, #e_param D>(deserializer: D) -> core::result::Result<#container, D::Error>
+        where
+            #e_bound
+            D: serde::Deserializer<'de>,
+        {
+            #borrow_cow_fn
+            #with_wrapper
+
+            /// Deserializes the element at `index`, adding its position to the errors the
+            /// element itself raises. Errors of the sequence around it are left alone.
+            struct Indexed<T> {
+                index: usize,
+                _casper: std::marker::PhantomData<T>,
+            }
+
+            impl<T> Indexed<T> {
+                fn new(index: usize) -> Self {
+                    Self {
+                        index,
+                        _casper: std::marker::PhantomData,
+                    }
+                }
+            }
+
+            impl<'de, T> serde::de::DeserializeSeed<'de> for Indexed<T>
+            where
+                T: serde::Deserialize<'de>,
+            {
+                type Value = T;
+
+                fn deserialize<D>(self, deserializer: D) -> core::result::Result<T, D::Error>
+                where
+                    D: serde::Deserializer<'de>,
+                {
+                    T::deserialize(deserializer).map_err(|err| {
+                        serde::de::Error::custom(std::format_args!(
+                            "invalid value at index {} of array of length {}: {}",
+                            self.index,
+                            #len,
+                            err,
+                        ))
+                    })
+                }
+            }
+
+            struct ArrayVisitor<E> {
+                _casper: std::marker::PhantomData<E>,
+            }
+
+            impl<E> ArrayVisitor<E> {
+                fn new() -> Self {
+                    Self {
+                        _casper: std::marker::PhantomData,
+                    }
+                }
+            }
+
+            impl<'de, #e_param> ArrayVisitor<#elem>
+            where
+                #e_bound
+            {
+                #visit_seq_paths
+            }
+
+            impl<'de, #e_param> serde::de::Visitor<'de> for ArrayVisitor<#elem>
+            where
+                #e_bound
+            {
+                type Value = #container;
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    std::write!(formatter, "an array of length {}", #len)
+                }
+
+                #[inline]
+                fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+                where
+                    A: serde::de::SeqAccess<'de>,
+                {
+                    #visit_seq
+                }
+            }
+
+            deserializer.deserialize_tuple(#len, ArrayVisitor::new())
+        }
+    }
+}
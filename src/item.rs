@@ -1,46 +1,148 @@
+mod container;
+mod default;
 mod fields;
 
 use proc_macro2::TokenStream;
-use quote::ToTokens;
+use quote::{quote, ToTokens};
 use syn::{
     parse::{Parse, ParseStream},
-    Generics, Ident,
+    parse_quote, Generics, Ident,
 };
 use syn::{Attribute, Field, ItemEnum, ItemStruct, Meta, NestedMeta};
 
-use fields::BigArrayField;
+pub use container::ContainerArgs;
+pub use fields::BigArrayField;
 
-/// Helper to search through a list of attributes for Serialize and Deserialize derives.
-fn check_if_serializing_deserializing<'a>(
+/// A `cfg` predicate something is conditional on, or `None` if it's unconditional.
+pub type Cfg = Option<TokenStream>;
+
+/// Combines predicates that all have to hold.
+pub fn cfg_all(mut preds: Vec<TokenStream>) -> Cfg {
+    match preds.len() {
+        0 => None,
+        1 => preds.pop(),
+        _ => Some(quote! { all(#(#preds),*) }),
+    }
+}
+
+/// Combines predicates any of which has to hold, for something done under each of them.
+/// Returns `None` if there are none, and `Some(None)` if one always holds.
+pub fn cfg_any(cfgs: Vec<Cfg>) -> Option<Cfg> {
+    if cfgs.is_empty() {
+        None
+    } else if cfgs.iter().any(Option::is_none) {
+        Some(None)
+    } else {
+        let mut preds: Vec<_> = cfgs.into_iter().flatten().collect();
+        if preds.len() == 1 {
+            Some(preds.pop())
+        } else {
+            Some(Some(quote! { any(#(#preds),*) }))
+        }
+    }
+}
+
+/// A Serde attribute with the given arguments, applied only if `cfg` holds.
+pub fn serde_attr(args: TokenStream, cfg: &Cfg) -> Attribute {
+    match cfg {
+        Some(pred) => parse_quote! { #[cfg_attr(#pred, serde(#args))] },
+        None => parse_quote! { #[serde(#args)] },
+    }
+}
+
+/// An item that's only compiled if `cfg` holds.
+pub fn gated(item: TokenStream, cfg: &Cfg) -> TokenStream {
+    match cfg {
+        Some(pred) => quote! { #[cfg(#pred)] #item },
+        None => item,
+    }
+}
+
+/// The predicate of a field's `#[cfg(...)]` attributes, if it has any.
+pub fn field_cfg(field: &Field) -> Cfg {
+    let preds = field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("cfg"))
+        .map(|attr| match attr.parse_meta() {
+            Ok(Meta::List(list)) => list.nested.into_token_stream(),
+            _ => panic!("expected cfg to have a predicate"),
+        })
+        .collect();
+
+    cfg_all(preds)
+}
+
+/// Helper to search through a list of attributes for Serialize and Deserialize derives,
+/// including ones made conditional with `cfg_attr`. Returns under what predicate each
+/// is derived, if at all.
+fn find_serde_derives<'a>(
     attrs: impl Iterator<Item = &'a Attribute>,
-) -> (bool, bool) {
-    let mut serialize = false;
-    let mut deserialize = false;
-
-    for derive_attr in attrs.filter(|attr| attr.path.is_ident("derive")) {
-        if let Meta::List(derive_attr) = derive_attr.parse_meta().unwrap() {
-            for derive in derive_attr.nested {
-                if let NestedMeta::Meta(Meta::Path(path)) = derive {
-                    // TODO: Is there a better way to make sure these are the derives we want?
-                    if let Some(last_segment) = path.segments.iter().last() {
-                        if last_segment.ident == "Serialize" {
-                            serialize = true;
-                        } else if last_segment.ident == "Deserialize" {
-                            deserialize = true;
+) -> (Option<Cfg>, Option<Cfg>) {
+    fn visit(
+        meta: Meta,
+        conds: &[TokenStream],
+        serialize: &mut Vec<Cfg>,
+        deserialize: &mut Vec<Cfg>,
+    ) {
+        match meta {
+            Meta::List(derive_attr) if derive_attr.path.is_ident("derive") => {
+                for derive in derive_attr.nested {
+                    if let NestedMeta::Meta(Meta::Path(path)) = derive {
+                        // TODO: Is there a better way to make sure these are the derives we want?
+                        if let Some(last_segment) = path.segments.iter().last() {
+                            if last_segment.ident == "Serialize" {
+                                serialize.push(cfg_all(conds.to_vec()));
+                            } else if last_segment.ident == "Deserialize" {
+                                deserialize.push(cfg_all(conds.to_vec()));
+                            }
+                        }
+                    };
+                }
+            }
+            Meta::List(cfg_attr) if cfg_attr.path.is_ident("cfg_attr") => {
+                let mut nested = cfg_attr.nested.into_iter();
+                if let Some(pred) = nested.next() {
+                    let conds = [conds, &[pred.into_token_stream()]].concat();
+                    for arg in nested {
+                        if let NestedMeta::Meta(meta) = arg {
+                            visit(meta, &conds, serialize, deserialize);
                         }
                     }
-                };
+                }
             }
+            _ => {}
         }
     }
 
-    (serialize, deserialize)
+    let mut serialize = vec![];
+    let mut deserialize = vec![];
+
+    for meta in attrs.filter_map(|attr| attr.parse_meta().ok()) {
+        visit(meta, &[], &mut serialize, &mut deserialize);
+    }
+
+    (cfg_any(serialize), cfg_any(deserialize))
+}
+
+/// Like `find_serde_derives`, but only checks whether the derives are there at all.
+#[cfg(test)]
+fn check_if_serializing_deserializing<'a>(
+    attrs: impl Iterator<Item = &'a Attribute>,
+) -> (bool, bool) {
+    let (serialize, deserialize) = find_serde_derives(attrs);
+
+    (serialize.is_some(), deserialize.is_some())
 }
 
 pub struct Context {
     pub type_name: String,
     pub serialize: bool,
     pub deserialize: bool,
+    /// The predicate the `Serialize` derive is conditional on.
+    pub serialize_cfg: Cfg,
+    /// The predicate the `Deserialize` derive is conditional on.
+    pub deserialize_cfg: Cfg,
     pub generics: Generics,
 }
 
@@ -58,18 +160,36 @@ impl Item {
     }
 
     pub fn context(&self) -> Context {
-        let (serialize, deserialize) = check_if_serializing_deserializing(self.attrs());
+        let (serialize, deserialize) = find_serde_derives(self.attrs());
 
         Context {
             type_name: self.ident().to_string(),
-            serialize,
-            deserialize,
+            serialize: serialize.is_some(),
+            deserialize: deserialize.is_some(),
+            serialize_cfg: serialize.flatten(),
+            deserialize_cfg: deserialize.flatten(),
             generics: self.generics().clone(),
         }
     }
 
-    pub fn big_array_fields(&mut self) -> impl Iterator<Item = BigArrayField> {
-        self.fields().filter_map(BigArrayField::parse_field)
+    /// The fields *Serbia* handles, given whether the container is deserialized at all.
+    pub fn big_array_fields<'a>(
+        &'a mut self,
+        args: &'a ContainerArgs,
+        deserializing: bool,
+    ) -> impl Iterator<Item = BigArrayField<'a>> {
+        self.fields()
+            .filter_map(move |field| BigArrayField::parse_field_in(field, args, deserializing))
+    }
+
+    /// Replaces a derived `Default` that big arrays would break with a generated impl,
+    /// which is returned.
+    pub fn take_default_derive(&mut self) -> Option<TokenStream> {
+        match self {
+            Item::Struct(s) => default::take_default_derive(s),
+            // Only unit variants can be derived defaults, so enums are fine as they are.
+            Item::Enum(_) => None,
+        }
     }
 
     fn fields(&mut self) -> impl Iterator<Item = &mut Field> {
@@ -77,7 +197,7 @@ impl Item {
             Item::Struct(s) => Box::new(s.fields.iter_mut()),
             Item::Enum(e) => {
                 let outer_iter = e.variants.iter_mut();
-                let result = outer_iter.map(|v| v.fields.iter_mut()).flatten();
+                let result = outer_iter.flat_map(|v| v.fields.iter_mut());
                 Box::new(result)
             }
         };
@@ -126,9 +246,13 @@ impl ToTokens for Item {
 
 #[cfg(test)]
 mod tests {
+    use quote::{quote, ToTokens};
     use syn::{parse_quote, Attribute, ItemStruct};
 
-    use super::{check_if_serializing_deserializing, BigArrayField};
+    use super::{
+        check_if_serializing_deserializing, find_serde_derives, BigArrayField, ContainerArgs, Item,
+    };
+    use crate::encoding::Encoding;
 
     #[test]
     fn parse_big_array_len() {
@@ -165,6 +289,189 @@ mod tests {
         assert!(BigArrayField::parse_field(&mut fields[2]).is_some());
     }
 
+    #[test]
+    fn custom_encoding_small_array() {
+        let s: ItemStruct = parse_quote! {
+            struct S {
+                a: [u32; 8],
+                #[serbia(delta)]
+                b: [u32; 8],
+            }
+        };
+
+        let mut fields: Vec<_> = s.fields.into_iter().collect();
+
+        assert!(BigArrayField::parse_field(&mut fields[0]).is_none());
+        assert!(BigArrayField::parse_field(&mut fields[1]).is_some());
+    }
+
+    #[test]
+    fn boxed_array() {
+        let s: ItemStruct = parse_quote! {
+            struct S {
+                a: Box<[u32; 32]>,
+                b: Box<[u32; 33]>,
+                c: std::sync::Arc<[u32; 33]>,
+                d: Box<u32>,
+                e: Vec<[u32; 33]>,
+            }
+        };
+
+        let mut fields: Vec<_> = s.fields.into_iter().collect();
+
+        assert!(BigArrayField::parse_field(&mut fields[0]).is_none());
+        assert!(BigArrayField::parse_field(&mut fields[1]).is_some());
+        assert!(BigArrayField::parse_field(&mut fields[2]).is_some());
+        assert!(BigArrayField::parse_field(&mut fields[3]).is_none());
+        assert!(BigArrayField::parse_field(&mut fields[4]).is_none());
+    }
+
+    #[test]
+    fn borrowed_array() {
+        let s: ItemStruct = parse_quote! {
+            struct S<'a> {
+                a: &'a [u8; 8],
+                b: &'a mut [u8; 33],
+                c: &'a [u8],
+            }
+        };
+
+        let mut fields: Vec<_> = s.fields.into_iter().collect();
+
+        assert!(BigArrayField::parse_field(&mut fields[0]).is_some());
+        assert!(BigArrayField::parse_field(&mut fields[1]).is_none());
+        assert!(BigArrayField::parse_field(&mut fields[2]).is_none());
+
+        // Serde serializes small ones fine, so containers that are only serialized
+        // keep its format.
+        let args = ContainerArgs::default();
+        assert!(BigArrayField::parse_field_in(&mut fields[0], &args, false).is_none());
+    }
+
+    #[test]
+    fn big_tuple() {
+        let s: ItemStruct = parse_quote! {
+            struct S {
+                a: (u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8),
+                b: (u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8),
+            }
+        };
+
+        let mut fields: Vec<_> = s.fields.into_iter().collect();
+
+        assert!(BigArrayField::parse_field(&mut fields[0]).is_none());
+        let field = BigArrayField::parse_field(&mut fields[1]).unwrap();
+        assert_eq!(field.tuple_types.unwrap().len(), 17);
+    }
+
+    #[test]
+    fn default_derive() {
+        let mut item: Item = parse_quote! {
+            #[derive(Debug, Default)]
+            struct S {
+                a: [u8; 33],
+            }
+        };
+        assert!(item.take_default_derive().is_some());
+        assert_eq!(
+            item.to_token_stream().to_string(),
+            quote!(
+                #[derive(Debug)]
+                struct S {
+                    a: [u8; 33],
+                }
+            )
+            .to_string()
+        );
+
+        let mut item: Item = parse_quote! {
+            #[derive(Default)]
+            struct S {
+                a: [u8; 32],
+            }
+        };
+        assert!(item.take_default_derive().is_none());
+
+        let mut item: Item = parse_quote! {
+            #[cfg_attr(feature = "x", derive(Default, Clone))]
+            #[cfg_attr(feature = "y", derive(Default))]
+            struct S {
+                a: [u8; 33],
+            }
+        };
+        let default_impl = item.take_default_derive().unwrap().to_string();
+        assert!(default_impl
+            .starts_with(&quote!(#[cfg(any(feature = "x", feature = "y"))]).to_string()));
+        assert_eq!(
+            item.to_token_stream().to_string(),
+            quote!(
+                #[cfg_attr(feature = "x", derive(Clone))]
+                struct S {
+                    a: [u8; 33],
+                }
+            )
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn zeroize_small_array() {
+        let s: ItemStruct = parse_quote! {
+            struct S {
+                a: [u8; 32],
+                #[serbia(zeroize)]
+                b: [u8; 32],
+            }
+        };
+
+        let mut fields: Vec<_> = s.fields.into_iter().collect();
+
+        assert!(BigArrayField::parse_field(&mut fields[0]).is_none());
+        assert!(BigArrayField::parse_field(&mut fields[1]).is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "serbia(rows) must be greater than zero")]
+    fn zero_rows() {
+        let s: ItemStruct = parse_quote! {
+            struct S {
+                #[serbia(rows = 0)]
+                a: [u8; 64],
+            }
+        };
+
+        let mut fields: Vec<_> = s.fields.into_iter().collect();
+
+        BigArrayField::parse_field(&mut fields[0]);
+    }
+
+    #[test]
+    fn safe_mode_has_no_unsafe() {
+        let s: ItemStruct = parse_quote! {
+            struct S {
+                #[serbia(safe)]
+                a: [String; 40],
+                #[serbia(safe, rows = 8)]
+                b: [u32; 64],
+                c: [String; 40],
+            }
+        };
+
+        let mut fields: Vec<_> = s.fields.into_iter().collect();
+        let ident = quote::format_ident!("de");
+        let render = |field: &mut syn::Field| {
+            let field = BigArrayField::parse_field(field).unwrap();
+            crate::encoding::render_deserialize_fn(&ident, &field).to_string()
+        };
+
+        assert!(!render(&mut fields[0]).contains("unsafe"));
+        assert!(!render(&mut fields[1]).contains("unsafe"));
+        assert_eq!(
+            render(&mut fields[2]).contains("unsafe"),
+            !cfg!(feature = "safe")
+        );
+    }
+
     #[test]
     fn no_serde_derive() {
         let attrs: Vec<Attribute> = vec![
@@ -276,4 +583,80 @@ mod tests {
             (true, true)
         );
     }
+
+    #[test]
+    fn detect_cfg_attr_derives() {
+        let attrs: Vec<Attribute> = vec![
+            parse_quote! {
+                #[derive(Debug)]
+            },
+            parse_quote! {
+                #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+            },
+            parse_quote! {
+                #[cfg_attr(feature = "a", cfg_attr(feature = "b", derive(Deserialize)))]
+            },
+        ];
+
+        let (serialize, deserialize) = find_serde_derives(attrs.iter());
+
+        assert_eq!(
+            serialize.unwrap().unwrap().to_string(),
+            quote!(feature = "serde").to_string()
+        );
+        assert_eq!(
+            deserialize.unwrap().unwrap().to_string(),
+            quote!(all(feature = "a", feature = "b")).to_string()
+        );
+    }
+
+    #[test]
+    fn container_args() {
+        let args: ContainerArgs = parse_quote!(threshold = 8, deserialize, delta);
+        assert_eq!(args.threshold, 8);
+        assert!(!args.serialize);
+        assert!(args.deserialize);
+        assert_eq!(args.field_args.len(), 1);
+
+        let s: ItemStruct = parse_quote! {
+            struct S {
+                a: [u32; 8],
+                b: [u32; 9],
+            }
+        };
+
+        let mut fields: Vec<_> = s.fields.into_iter().collect();
+
+        assert!(BigArrayField::parse_field_in(&mut fields[0], &args, true).is_none());
+        let field = BigArrayField::parse_field_in(&mut fields[1], &args, true).unwrap();
+        assert!(matches!(field.encoding, Encoding::Delta { zigzag: false }));
+    }
+
+    #[test]
+    fn aliases() {
+        let args: ContainerArgs = parse_quote!(alias(Key = 64, Block = "BLOCK_LEN"));
+        let s: ItemStruct = parse_quote! {
+            struct S {
+                a: Key,
+                b: Option<Block>,
+                c: Option<[u8; 32]>,
+                d: Option<[u8; 33]>,
+            }
+        };
+
+        let mut fields: Vec<_> = s.fields.into_iter().collect();
+
+        let field = BigArrayField::parse_field_in(&mut fields[0], &args, true).unwrap();
+        assert_eq!(field.len.to_string(), "64");
+        assert!(!field.optional);
+        let field = BigArrayField::parse_field_in(&mut fields[1], &args, true).unwrap();
+        assert_eq!(field.len.to_string(), "BLOCK_LEN");
+        assert!(field.optional);
+        assert!(BigArrayField::parse_field_in(&mut fields[2], &args, true).is_none());
+        assert!(
+            BigArrayField::parse_field_in(&mut fields[3], &args, true)
+                .unwrap()
+                .optional
+        );
+    }
 }
@@ -0,0 +1,105 @@
+use proc_macro2::TokenStream;
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_quote,
+    punctuated::Punctuated,
+    Ident, Lit, Meta, NestedMeta, Token,
+};
+
+use super::fields::parse_len;
+use crate::encoding::render_len_check;
+
+/// Options given to `#[serbia(...)]` itself, for the whole container.
+pub struct ContainerArgs {
+    /// Arrays with more elements than this are handled, even without any field options.
+    pub threshold: usize,
+    /// Whether to generate serialization code even without a `Serialize` derive.
+    pub serialize: bool,
+    /// Whether to generate deserialization code even without a `Deserialize` derive.
+    pub deserialize: bool,
+    /// Field options that every big array field inherits, unless it overrides them.
+    pub field_args: Vec<NestedMeta>,
+    /// Type aliases of arrays, with their lengths.
+    pub aliases: Vec<(Ident, TokenStream)>,
+}
+
+impl Default for ContainerArgs {
+    fn default() -> Self {
+        Self {
+            threshold: 32,
+            serialize: false,
+            deserialize: false,
+            field_args: vec![],
+            aliases: vec![],
+        }
+    }
+}
+
+impl ContainerArgs {
+    /// Renders compile-time checks that the aliases really are arrays of the given lengths.
+    pub fn render_alias_checks(&self) -> TokenStream {
+        self.aliases
+            .iter()
+            .map(|(alias, len)| {
+                render_len_check(
+                    &parse_quote!(#alias),
+                    len,
+                    alias.span(),
+                    &format!("alias {} = {} but {}", alias, len, alias),
+                )
+            })
+            .collect()
+    }
+}
+
+impl Parse for ContainerArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = Self::default();
+
+        for arg in Punctuated::<NestedMeta, Token![,]>::parse_terminated(input)? {
+            let key = match &arg {
+                NestedMeta::Meta(meta) => meta.path().get_ident().map(|ident| ident.to_string()),
+                NestedMeta::Lit(_) => None,
+            }
+            .expect("expected serbia argument to be an ident");
+
+            match (key.as_str(), &arg) {
+                ("all", NestedMeta::Meta(Meta::Path(_))) => args.threshold = 0,
+                ("serialize", NestedMeta::Meta(Meta::Path(_))) => args.serialize = true,
+                ("deserialize", NestedMeta::Meta(Meta::Path(_))) => args.deserialize = true,
+                ("threshold", NestedMeta::Meta(Meta::NameValue(meta))) => {
+                    args.threshold = match &meta.lit {
+                        Lit::Int(threshold) => threshold.base10_parse()?,
+                        _ => panic!("expected serbia(threshold) to be an integer"),
+                    };
+                    if args.threshold > 32 {
+                        panic!("serbia(threshold) can't be over 32, since Serde can't handle longer arrays");
+                    }
+                }
+                ("alias", NestedMeta::Meta(Meta::List(list))) => {
+                    for alias in &list.nested {
+                        match alias {
+                            NestedMeta::Meta(Meta::NameValue(alias))
+                                if alias.path.get_ident().is_some() =>
+                            {
+                                args.aliases.push((
+                                    alias.path.get_ident().unwrap().clone(),
+                                    parse_len(alias.lit.clone()),
+                                ));
+                            }
+                            _ => panic!(
+                                "expected serbia(alias(...)) to be a list of `Alias = length`"
+                            ),
+                        }
+                    }
+                }
+                ("skip" | "skip_serializing" | "skip_deserializing" | "bufsize" | "default", _) => {
+                    panic!("serbia({}) can only be used on fields", key)
+                }
+                _ => args.field_args.push(arg),
+            }
+        }
+
+        Ok(args)
+    }
+}
@@ -0,0 +1,170 @@
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::{
+    parse_quote, Attribute, Expr, ExprLit, Fields, Generics, ItemStruct, Lit, Meta, NestedMeta,
+    Type, WherePredicate,
+};
+
+use super::{cfg_all, cfg_any, gated, Cfg};
+use crate::encoding::type_params;
+
+/// Removes `Default` from the derives of a struct that `#[derive(Default)]` can't handle
+/// because of big arrays, and returns an equivalent impl to use instead. Derives made
+/// conditional with `cfg_attr` get an impl under the same predicate.
+pub fn take_default_derive(s: &mut ItemStruct) -> Option<TokenStream> {
+    if !s.fields.iter().any(|field| needs_element_wise(&field.ty)) {
+        return None;
+    }
+
+    let mut derived = vec![];
+
+    s.attrs = std::mem::take(&mut s.attrs)
+        .into_iter()
+        .filter_map(|attr| {
+            let meta = match attr.parse_meta() {
+                Ok(meta) if attr.path.is_ident("derive") || attr.path.is_ident("cfg_attr") => meta,
+                _ => return Some(attr),
+            };
+
+            let found = derived.len();
+            let rest = take_default(meta, &[], &mut derived);
+            if derived.len() == found {
+                return Some(attr);
+            }
+            rest.map(|meta| {
+                let attr: Attribute = parse_quote! { #[#meta] };
+                attr
+            })
+        })
+        .collect();
+
+    cfg_any(derived).map(|cfg| gated(render_default_impl(s), &cfg))
+}
+
+/// Removes `Default` from a `derive`, including one in a `cfg_attr`, and records the
+/// predicate it was derived under. Returns what's left of the attribute, if anything.
+fn take_default(meta: Meta, conds: &[TokenStream], derived: &mut Vec<Cfg>) -> Option<Meta> {
+    match meta {
+        Meta::List(mut list) if list.path.is_ident("derive") => {
+            list.nested = std::mem::take(&mut list.nested)
+                .into_iter()
+                .filter(|derive| {
+                    let is_default = matches!(
+                        derive,
+                        NestedMeta::Meta(Meta::Path(path))
+                            if path.segments.iter().last().is_some_and(|s| s.ident == "Default")
+                    );
+                    if is_default {
+                        derived.push(cfg_all(conds.to_vec()));
+                    }
+                    !is_default
+                })
+                .collect();
+
+            if list.nested.is_empty() {
+                None
+            } else {
+                Some(Meta::List(list))
+            }
+        }
+        Meta::List(mut list) if list.path.is_ident("cfg_attr") => {
+            let mut nested = std::mem::take(&mut list.nested).into_iter();
+            let pred = nested.next()?;
+            let conds = [conds, &[pred.to_token_stream()]].concat();
+            let rest: Vec<_> = nested
+                .filter_map(|arg| match arg {
+                    NestedMeta::Meta(meta) => {
+                        take_default(meta, &conds, derived).map(NestedMeta::Meta)
+                    }
+                    lit => Some(lit),
+                })
+                .collect();
+
+            if rest.is_empty() {
+                None
+            } else {
+                list.nested = std::iter::once(pred).chain(rest).collect();
+                Some(Meta::List(list))
+            }
+        }
+        meta => Some(meta),
+    }
+}
+
+/// Checks if a type is an array the standard library doesn't implement `Default` for.
+fn needs_element_wise(ty: &Type) -> bool {
+    match ty {
+        Type::Array(array_type) => match &array_type.len {
+            Expr::Lit(ExprLit {
+                lit: Lit::Int(len), ..
+            }) => len.base10_parse::<usize>().unwrap() > 32 || needs_element_wise(&array_type.elem),
+            _ => true,
+        },
+        _ => false,
+    }
+}
+
+/// An expression building the default value of `ty`, arrays element by element. Types that
+/// aren't arrays have their `Default` bound collected in `bounds`.
+fn render_default(ty: &Type, bounds: &mut Vec<Type>) -> TokenStream {
+    match ty {
+        Type::Array(array_type) => {
+            let elem = render_default(&array_type.elem, bounds);
+            quote! { core::array::from_fn(|_| #elem) }
+        }
+        ty => {
+            let ty_str = ty.to_token_stream().to_string();
+            if !bounds
+                .iter()
+                .any(|bound| bound.to_token_stream().to_string() == ty_str)
+            {
+                bounds.push(ty.clone());
+            }
+            quote! { core::default::Default::default() }
+        }
+    }
+}
+
+fn render_default_impl(s: &ItemStruct) -> TokenStream {
+    let mut bounds = vec![];
+    // Fields behind `cfg` get their initializers behind the same `cfg`.
+    let values: Vec<_> = s
+        .fields
+        .iter()
+        .map(|field| {
+            let cfgs = field.attrs.iter().filter(|attr| attr.path.is_ident("cfg"));
+            let value = render_default(&field.ty, &mut bounds);
+            match &field.ident {
+                Some(name) => quote! { #(#cfgs)* #name: #value },
+                None => quote! { #(#cfgs)* #value },
+            }
+        })
+        .collect();
+
+    let body = match &s.fields {
+        Fields::Named(_) => quote! { Self { #(#values,)* } },
+        Fields::Unnamed(_) => quote! { Self(#(#values,)*) },
+        Fields::Unit => quote! { Self },
+    };
+
+    // Like Serde, only bound what the fields need, rather than every type parameter.
+    let mut generics: Generics = s.generics.clone();
+    let where_clause = generics.make_where_clause();
+    for ty in bounds {
+        if !type_params(&ty, &s.generics).is_empty() {
+            let predicate: WherePredicate = parse_quote! { #ty: core::default::Default };
+            where_clause.predicates.push(predicate);
+        }
+    }
+
+    let ident = &s.ident;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics core::default::Default for #ident #ty_generics #where_clause {
+            fn default() -> Self {
+                #body
+            }
+        }
+    }
+}
@@ -1,6 +1,12 @@
-use proc_macro2::TokenStream;
-use quote::ToTokens;
-use syn::{Expr, ExprLit, Field, Ident, Lit, Meta, MetaList, NestedMeta, Type};
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, ToTokens};
+use syn::{
+    parse_quote, Expr, ExprLit, ExprPath, Field, GenericArgument, Ident, Lit, Meta, MetaList,
+    NestedMeta, PathArguments, Type, TypePath,
+};
+
+use super::ContainerArgs;
+use crate::encoding::{Encoding, Endianness, Pointer};
 
 enum Arg {
     Flag(String),
@@ -33,6 +39,92 @@ fn parse_arg(meta: NestedMeta) -> Result<Arg, ()> {
     Err(())
 }
 
+/// The type in an `Option`, if that's what `ty` is.
+fn option_arg(ty: &Type) -> Option<&Type> {
+    let segment = match ty {
+        Type::Path(TypePath { qself: None, path }) => path.segments.iter().last()?,
+        _ => return None,
+    };
+    if segment.ident != "Option" {
+        return None;
+    }
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Parses a length given either as an integer literal or as a string with the name
+/// of a constant.
+pub fn parse_len(value: Lit) -> TokenStream {
+    if let Lit::Str(const_name) = value {
+        Ident::new(&const_name.value(), const_name.span()).to_token_stream()
+    } else {
+        value.into_token_stream()
+    }
+}
+
+/// Parses a path given as a string, like the ones in Serde's `serialize_with`.
+fn parse_path(value: &Lit, option: &str) -> ExprPath {
+    match value {
+        Lit::Str(path) => path
+            .parse()
+            .unwrap_or_else(|_| panic!("expected serbia({}) to be a path", option)),
+        _ => panic!("expected serbia({}) to be a string", option),
+    }
+}
+
+/// Checks if a Serde attribute argument is a `default` without a function.
+fn is_bare_default(arg: &NestedMeta) -> bool {
+    matches!(arg, NestedMeta::Meta(Meta::Path(path)) if path.is_ident("default"))
+}
+
+/// Arguments of a Serde attribute, including ones applied with `cfg_attr`.
+fn serde_args(meta: Meta) -> Vec<NestedMeta> {
+    match meta {
+        Meta::List(list) if list.path.is_ident("serde") => list.nested.into_iter().collect(),
+        Meta::List(list) if list.path.is_ident("cfg_attr") => list
+            .nested
+            .into_iter()
+            .skip(1)
+            .flat_map(|arg| match arg {
+                NestedMeta::Meta(meta) => serde_args(meta),
+                NestedMeta::Lit(_) => vec![],
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Replaces bare `default` arguments of a Serde attribute, including ones applied with
+/// `cfg_attr`, with `default = "fn_name"`. Returns whether there were any.
+fn point_default_at(meta: &mut Meta, fn_name: &str) -> bool {
+    let list = match meta {
+        Meta::List(list) => list,
+        _ => return false,
+    };
+    let mut found = false;
+
+    if list.path.is_ident("serde") {
+        for arg in list.nested.iter_mut().filter(|arg| is_bare_default(arg)) {
+            *arg = parse_quote!(default = #fn_name);
+            found = true;
+        }
+    } else if list.path.is_ident("cfg_attr") {
+        for arg in list.nested.iter_mut().skip(1) {
+            if let NestedMeta::Meta(meta) = arg {
+                found |= point_default_at(meta, fn_name);
+            }
+        }
+    }
+
+    found
+}
+
 /// A field that is a (potentially) big array, with convenient metadata
 /// for generating custom serialization/deserialization code.
 pub struct BigArrayField<'f> {
@@ -41,15 +133,92 @@ pub struct BigArrayField<'f> {
     pub serialize: bool,
     pub deserialize: bool,
     pub element_type: Option<Type>,
+    pub encoding: Encoding,
+    pub element_serialize_with: Option<ExprPath>,
+    pub element_deserialize_with: Option<ExprPath>,
+    pub validate_element: Option<ExprPath>,
+    pub validate: Option<ExprPath>,
+    pub default_on_element_error: bool,
+    pub element_error_hook: Option<ExprPath>,
+    pub safe: bool,
+    pub zeroize: bool,
+    pub pointer: Option<Pointer>,
+    /// Whether the field has Serde's `borrow` attribute.
+    pub borrow: bool,
+    /// Element types of a tuple too long for Serde, if that's what the field is.
+    pub tuple_types: Option<Vec<Type>>,
+    /// Whether a default function should be generated, because of Serde's bare `default`
+    /// attribute or `serbia(default = ...)`.
+    pub default: bool,
+    /// The value arrays are filled with by default, instead of `Default::default()`.
+    pub default_value: Option<Expr>,
+    /// Whether the array is in an `Option`.
+    pub optional: bool,
+    /// Whether a missing `Option` should still deserialize to `None`, which Serde
+    /// only does by itself for fields without `deserialize_with`.
+    pub default_none: bool,
+    /// The type of the array itself, which is the field type unless it's in an `Option`.
+    pub ty: Type,
+    /// Where the length was given with `serbia(bufsize)`, if it was.
+    pub bufsize: Option<Span>,
+    /// Whether the length comes from a type alias declared on the container.
+    pub aliased: bool,
+    /// The module with the container's `Lenient` wrapper, which fields replacing invalid
+    /// elements with defaults are deserialized through. Set once the container is known.
+    pub lenient_mod: Option<Ident>,
 }
 
 impl<'f> BigArrayField<'f> {
-    // TODO: This begs for a cleanup. Convoluted logic.
+    /// Points Serde's bare `default` attribute at the given function. Returns `false` if
+    /// the field doesn't have one.
+    pub fn point_default_at(&mut self, fn_name: &str) -> bool {
+        let mut found = false;
+
+        for attr in &mut self.field.attrs {
+            if let Ok(mut meta) = attr.parse_meta() {
+                if point_default_at(&mut meta, fn_name) {
+                    *attr = parse_quote!(#[#meta]);
+                    found = true;
+                }
+            }
+        }
+
+        found
+    }
+
+    #[cfg(test)]
     pub fn parse_field(field: &'f mut Field) -> Option<Self> {
+        Self::parse_field_in(field, &ContainerArgs::default(), true)
+    }
+
+    // TODO: This begs for a cleanup. Convoluted logic.
+    pub fn parse_field_in(
+        field: &'f mut Field,
+        container: &ContainerArgs,
+        deserializing: bool,
+    ) -> Option<Self> {
         let mut len = None;
         let mut serialize = true;
         let mut deserialize = true;
         let mut element_type = None;
+        let mut encoding = None;
+        let mut container_encoding = None;
+        let mut container_zeroize = false;
+        let mut rows_in_binary = false;
+        let mut element_serialize_with = None;
+        let mut element_deserialize_with = None;
+        let mut validate_element = None;
+        let mut validate = None;
+        let mut default_on_element_error = false;
+        let mut element_error_hook = None;
+        let mut safe = false;
+        let mut zeroize = false;
+        let mut borrow = false;
+        let mut tuple_types = None;
+        let mut default = false;
+        let mut default_fn = false;
+        let mut default_value = None;
+        let mut bufsize = None;
 
         // TODO: replace with drain_filter once stabilized.
         let (serbia_attrs, other_attrs): (Vec<_>, Vec<_>) =
@@ -63,61 +232,204 @@ impl<'f> BigArrayField<'f> {
 
         field.attrs = other_attrs;
 
-        let serde_attrs = field.attrs.iter().filter(|a| {
-            if let Some(ident) = a.path.get_ident() {
-                return ident == "serde";
-            }
+        let serde_args: Vec<_> = field
+            .attrs
+            .iter()
+            .filter_map(|a| a.parse_meta().ok())
+            .flat_map(serde_args)
+            .collect();
 
-            false
-        });
+        let field_args: Vec<NestedMeta> = serbia_attrs
+            .into_iter()
+            .flat_map(|attr| match attr.parse_meta().unwrap() {
+                Meta::List(MetaList { nested, .. }) => nested.into_iter().collect(),
+                _ => vec![],
+            })
+            .collect();
 
-        for attr in serbia_attrs {
-            if let Meta::List(MetaList { nested: meta, .. }) = attr.parse_meta().unwrap() {
-                for arg in meta {
-                    let arg = parse_arg(arg).unwrap();
+        // Container options come first, so that the field's own override them.
+        for (inherited, args) in [(true, container.field_args.clone()), (false, field_args)] {
+            for arg in args {
+                let arg = parse_arg(arg).unwrap();
 
-                    match arg {
-                        Arg::Flag(flag) => match flag.as_str() {
-                            "skip" => return None,
-                            "skip_serializing" => serialize = false,
-                            "skip_deserializing" => deserialize = false,
-                            unknown => panic!("unknown serbia flag: {}", unknown),
+                match arg {
+                    Arg::Flag(flag) => match flag.as_str() {
+                        "skip" => return None,
+                        "skip_serializing" => serialize = false,
+                        "skip_deserializing" => deserialize = false,
+                        "tuple" => encoding = Some(Encoding::Tuple),
+                        "delta" => encoding = Some(Encoding::Delta { zigzag: false }),
+                        "delta_zigzag" => encoding = Some(Encoding::Delta { zigzag: true }),
+                        "rows_in_binary" => rows_in_binary = true,
+                        "safe" => safe = true,
+                        "zeroize" => zeroize = true,
+                        "cstr" => {
+                            encoding = Some(Encoding::Text {
+                                nul_terminated: true,
+                            })
+                        }
+                        "utf8" => {
+                            encoding = Some(Encoding::Text {
+                                nul_terminated: false,
+                            })
+                        }
+                        unknown => panic!("unknown serbia flag: {}", unknown),
+                    },
+                    Arg::KeyValueArg(key_value) => match key_value.key.as_str() {
+                        "bufsize" => {
+                            bufsize = Some(key_value.value.span());
+                            len = Some(parse_len(key_value.value));
+                        }
+                        "delimited" => match key_value.value {
+                            Lit::Str(delimiter) if !delimiter.value().is_empty() => {
+                                encoding = Some(Encoding::Delimited { delimiter })
+                            }
+                            _ => panic!("expected serbia(delimited) to be a non-empty string"),
                         },
-                        Arg::KeyValueArg(key_value) => match key_value.key.as_str() {
-                            "bufsize" => {
-                                len = Some(if let Lit::Str(const_name) = key_value.value {
-                                    Ident::new(&const_name.value(), const_name.span())
-                                        .to_token_stream()
-                                } else {
-                                    key_value.value.into_token_stream()
-                                });
+                        "element_with" => {
+                            let module = parse_path(&key_value.value, "element_with");
+                            element_serialize_with = Some(parse_quote!(#module::serialize));
+                            element_deserialize_with = Some(parse_quote!(#module::deserialize));
+                        }
+                        "element_serialize_with" => {
+                            element_serialize_with =
+                                Some(parse_path(&key_value.value, "element_serialize_with"))
+                        }
+                        "element_deserialize_with" => {
+                            element_deserialize_with =
+                                Some(parse_path(&key_value.value, "element_deserialize_with"))
+                        }
+                        "validate_element" => {
+                            validate_element =
+                                Some(parse_path(&key_value.value, "validate_element"))
+                        }
+                        "validate" => validate = Some(parse_path(&key_value.value, "validate")),
+                        "default" => {
+                            default_value = Some(match key_value.value {
+                                Lit::Str(expr) => expr
+                                    .parse()
+                                    .expect("expected serbia(default) to be an expression"),
+                                lit => Expr::Lit(ExprLit { attrs: vec![], lit }),
+                            })
+                        }
+                        "on_element_error" => match &key_value.value {
+                            Lit::Str(action) if action.value() == "default" => {
+                                default_on_element_error = true
                             }
-                            unknown => panic!("unknown serbia key-value option: {}", unknown),
+                            _ => panic!("expected serbia(on_element_error) to be \"default\""),
                         },
-                    }
+                        "element_error_hook" => {
+                            element_error_hook =
+                                Some(parse_path(&key_value.value, "element_error_hook"))
+                        }
+                        "codec" => match key_value.value {
+                            Lit::Str(codec) => {
+                                encoding = Some(Encoding::Codec(Box::new(
+                                    codec.parse().expect("expected serbia(codec) to be a type"),
+                                )))
+                            }
+                            _ => panic!("expected serbia(codec) to be a string"),
+                        },
+                        "rows" => {
+                            if matches!(&key_value.value, Lit::Int(rows) if matches!(rows.base10_parse(), Ok(0usize)))
+                            {
+                                panic!("serbia(rows) must be greater than zero");
+                            }
+                            encoding = Some(Encoding::Rows {
+                                row_len: parse_len(key_value.value),
+                                in_binary: false,
+                            })
+                        }
+                        "packed" => {
+                            let endianness = match &key_value.value {
+                                Lit::Str(endianness) => Endianness::parse(&endianness.value()),
+                                _ => None,
+                            };
+                            encoding =
+                                Some(Encoding::Packed(endianness.expect(
+                                    "expected serbia(packed) to be either \"le\" or \"be\"",
+                                )));
+                        }
+                        unknown => panic!("unknown serbia key-value option: {}", unknown),
+                    },
                 }
             }
+
+            // Inherited encodings and wiping only apply to fields that are big arrays
+            // regardless, so they're set aside until that's known.
+            if inherited {
+                container_encoding = encoding.take();
+                container_zeroize = std::mem::take(&mut zeroize);
+            }
         }
 
-        for attr in serde_attrs {
-            if let Meta::List(MetaList { nested: meta, .. }) = attr.parse_meta().unwrap() {
-                for arg in meta {
-                    if let NestedMeta::Meta(meta) = arg {
-                        match meta.path().get_ident().unwrap().to_string().as_str() {
-                            "serialize_with" | "skip_serializing" => serialize = false,
-                            "deserialize_with" | "skip_deserializing" => deserialize = false,
-                            "skip" | "with" => {
-                                serialize = false;
-                                deserialize = false;
-                            }
-                            _ => {}
-                        }
+        if element_error_hook.is_some() && !default_on_element_error {
+            panic!("serbia(element_error_hook) requires serbia(on_element_error = \"default\")");
+        }
+
+        for arg in serde_args {
+            if let NestedMeta::Meta(meta) = arg {
+                match meta.path().get_ident().unwrap().to_string().as_str() {
+                    // Only a bare `default` relies on the array implementing `Default`.
+                    "default" if matches!(meta, Meta::Path(_)) => default = true,
+                    "default" => default_fn = true,
+                    "serialize_with" | "skip_serializing" => serialize = false,
+                    "deserialize_with" | "skip_deserializing" => deserialize = false,
+                    "skip" | "with" => {
+                        serialize = false;
+                        deserialize = false;
                     }
+                    "borrow" => borrow = true,
+                    _ => {}
+                }
+            }
+        }
+
+        if default_value.is_some() && default_fn {
+            panic!("serbia(default) can't be combined with serde(default = ...)");
+        }
+        default |= default_value.is_some();
+
+        // Arrays in an `Option` are handled like the rest, and wrapped in one afterwards.
+        let (optional, ty) = match option_arg(&field.ty) {
+            Some(ty) => (true, ty.clone()),
+            None => (false, field.ty.clone()),
+        };
+
+        let default_none = optional && !default && !default_fn;
+        if optional {
+            if default_value.is_some() {
+                panic!("serbia(default) can't be used on an Option");
+            }
+            // Serde's own default for an `Option` is `None`.
+            default = false;
+        }
+
+        // Types declared as aliases of arrays on the container.
+        let mut aliased = false;
+        if let Type::Path(TypePath { qself: None, path }) = &ty {
+            if let Some((_, alias_len)) = container
+                .aliases
+                .iter()
+                .find(|(alias, _)| path.is_ident(alias))
+            {
+                if len.is_none() {
+                    len = Some(alias_len.clone());
+                    aliased = true;
                 }
             }
         }
 
-        if let Type::Array(array_type) = &field.ty {
+        // The array is either the field itself or in a smart pointer.
+        let (pointer, array_type) = match &ty {
+            Type::Array(array_type) => (None, Some(array_type)),
+            ty => match Pointer::parse(ty) {
+                Some((pointer, array_type)) => (Some(pointer), Some(array_type)),
+                None => (None, None),
+            },
+        };
+
+        if let Some(array_type) = array_type {
             element_type = Some(*array_type.elem.clone());
 
             if len.is_none() {
@@ -128,13 +440,51 @@ impl<'f> BigArrayField<'f> {
                 {
                     let len_literal: usize = len_literal.base10_parse().unwrap();
 
-                    if len_literal > 32 {
+                    // Arrays Serde can handle are left alone, unless a custom encoding,
+                    // custom element (de)serializers, validation, recovery or wiping were
+                    // explicitly requested. Serde can't borrow arrays of any length, but
+                    // references it only serializes keep its format.
+                    if len_literal > container.threshold
+                        || encoding.as_ref().is_some_and(|e| !e.is_default())
+                        || element_serialize_with.is_some()
+                        || element_deserialize_with.is_some()
+                        || validate_element.is_some()
+                        || validate.is_some()
+                        || default_on_element_error
+                        || zeroize
+                        || default_value.is_some()
+                        || (pointer == Some(Pointer::Ref) && deserializing)
+                    {
                         len = Some(array_type.len.clone().into_token_stream());
                     }
                 } else if let Expr::Path(len_expr) = &array_type.len {
                     len = Some(len_expr.into_token_stream());
+                } else {
+                    // Some other constant expression, like `1 << 20`. It's parenthesized
+                    // so that it can be used in arithmetic.
+                    let len_expr = &array_type.len;
+                    len = Some(quote! { (#len_expr) });
                 }
             }
+        } else if let Type::Tuple(tuple_type) = &ty {
+            // Serde only implements its traits for tuples of up to 16 elements.
+            if tuple_type.elems.len() > 16 {
+                len = Some(tuple_type.elems.len().into_token_stream());
+                tuple_types = Some(tuple_type.elems.iter().cloned().collect());
+            }
+        }
+
+        let mut encoding = encoding.or(container_encoding).unwrap_or_default();
+        let zeroize = zeroize || container_zeroize;
+        // Wiping needs the in-place deserializer, so it takes precedence over the cargo
+        // feature. Some other crate in the build might have turned that on.
+        let safe = safe || (cfg!(feature = "safe") && !zeroize);
+
+        if rows_in_binary {
+            match &mut encoding {
+                Encoding::Rows { in_binary, .. } => *in_binary = true,
+                _ => panic!("serbia(rows_in_binary) requires serbia(rows = ...)"),
+            }
         }
 
         if let Some(len) = len {
@@ -144,6 +494,26 @@ impl<'f> BigArrayField<'f> {
                 serialize,
                 deserialize,
                 element_type,
+                encoding,
+                element_serialize_with,
+                element_deserialize_with,
+                validate_element,
+                validate,
+                default_on_element_error,
+                element_error_hook,
+                safe,
+                zeroize,
+                pointer,
+                borrow,
+                tuple_types,
+                default,
+                default_value,
+                optional,
+                default_none,
+                ty,
+                bufsize,
+                aliased,
+                lenient_mod: None,
             });
         }
 
@@ -2,92 +2,18 @@
 
 extern crate proc_macro;
 
+mod encoding;
 mod item;
 
-use proc_macro2::{Ident, TokenStream};
-use quote::{format_ident, quote, ToTokens};
-use syn::{parse_macro_input, parse_quote, Type, TypePath};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Type, TypePath};
 
-use crate::item::Item;
-
-fn render_serialize_fn(fn_ident: &Ident, len: impl ToTokens) -> TokenStream {
-    quote! {
-        fn #fn_ident<E, S>(array: &[E; #len], serializer: S) -> core::result::Result<S::Ok, S::Error>
-        where
-            E: serde::Serialize,
-            S: serde::Serializer,
-        {
-            use serde::ser::SerializeTuple;
-
-            let mut seq = serializer.serialize_tuple(#len)?;
-            for e in array {
-                seq.serialize_element(e)?;
-            }
-            seq.end()
-        }
-    }
-}
-
-fn render_deserialize_fn(fn_ident: &Ident, len: impl ToTokens) -> TokenStream {
-    quote! {
-        fn #fn_ident<'de, E, D>(deserializer: D) -> core::result::Result<[E; #len], D::Error>
-        where
-            E: serde::Deserialize<'de>,
-            D: serde::Deserializer<'de>,
-        {
-            struct ArrayVisitor<E> {
-                _casper: std::marker::PhantomData<E>,
-            }
-
-            impl<E> ArrayVisitor<E> {
-                fn new() -> Self {
-                    Self {
-                        _casper: std::marker::PhantomData,
-                    }
-                }
-            }
-
-            impl<'de, E> serde::de::Visitor<'de> for ArrayVisitor<E>
-            where
-                E: serde::Deserialize<'de>,
-            {
-                type Value = [E; #len];
-
-                fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-                    formatter.write_str(std::concat!("an array"))
-                }
-
-                #[inline]
-                fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
-                where
-                    A: serde::de::SeqAccess<'de>,
-                {
-                    use std::mem::{self, MaybeUninit};
-
-                    let mut arr: [MaybeUninit<E>; #len] = unsafe { MaybeUninit::uninit().assume_init() };
-
-                    for (i, v) in arr.iter_mut().enumerate() {
-                        *v = MaybeUninit::new(match seq.next_element()? {
-                            Some(val) => val,
-                            None => {
-                                (&mut arr[0..i]).iter_mut().for_each(|elem| {
-                                    // TODO This would be better with assume_init_drop nightly function
-                                    // https://github.com/rust-lang/rust/issues/63567
-                                    unsafe { std::ptr::drop_in_place(elem.as_mut_ptr()) };
-                                });
-                                return Err(serde::de::Error::invalid_length(i, &self));
-                            }
-                        });
-                    }
-
-                    Ok(unsafe { mem::transmute_copy::<_, [E; #len]>(&arr) })
-                }
-            }
-
-            deserializer.deserialize_tuple(#len, ArrayVisitor::new())
-        }
-    }
-}
+use crate::encoding::{
+    contains_reference, deserialize_bounds, lifetimes, render_default_fn, render_deserialize_fn,
+    render_len_check, render_len_const, render_lenient_mod, render_rows_check, render_serialize_fn,
+    serialize_bounds, type_params,
+};
+use crate::item::{cfg_all, field_cfg, gated, serde_attr, ContainerArgs, Item};
 
 /// An attribute macro that enables (de)serializing arrays of length larger than 32 with [Serde](serde).
 ///
@@ -209,6 +135,514 @@ fn render_deserialize_fn(fn_ident: &Ident, len: impl ToTokens) -> TokenStream {
 /// }
 /// ```
 ///
+/// The length is checked against the field type at compile time. This fails with
+/// "bufsize = 300 but the field type is `[i32; 256]`", pointing at the `bufsize`.
+///
+/// ```compile_fail,E0277
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// #
+/// type BigArray = [i32; 256];
+///
+/// #[serbia]
+/// #[derive(Serialize, Deserialize)]
+/// struct S {
+///     #[serbia(bufsize = 300)]
+///     arr_a: BigArray,
+/// }
+/// ```
+///
+/// ## Per-element (de)serializers
+///
+/// Elements of a big array can be (de)serialized with custom functions using
+/// `#[serbia(element_with = "module")]`, `#[serbia(element_serialize_with = "path")]`
+/// and `#[serbia(element_deserialize_with = "path")]`. These work just like Serde's
+/// `with`, `serialize_with` and `deserialize_with`, except they're applied to each
+/// element, to arrays of any length. The element type has to be spelled out in the
+/// field type and can't depend on type parameters.
+///
+/// ```rust
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// # mod u64_as_string {
+/// #     pub fn serialize<S: serde::Serializer>(n: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+/// #         serializer.collect_str(n)
+/// #     }
+/// #     pub fn deserialize<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+/// #         unimplemented!()
+/// #     }
+/// # }
+/// #
+/// #[serbia]
+/// #[derive(Serialize, Deserialize)]
+/// struct S {
+///     #[serbia(element_with = "u64_as_string")]
+///     ids: [u64; 40],
+/// }
+/// ```
+///
+/// ## Validation
+///
+/// Deserialized arrays can be validated with `#[serbia(validate_element = "path")]`,
+/// called with the index and a reference to each element, and
+/// `#[serbia(validate = "path")]`, called with a reference to the whole array.
+/// Both should return a `Result<(), E>` where `E: Display`. An `Err` fails
+/// deserialization, with the index of the element in the error message. Arrays of
+/// any length are validated.
+///
+/// ```rust
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// # fn palette_index(i: usize, index: &u8) -> Result<(), String> { Ok(()) }
+/// # fn normalized(weights: &[f32; 64]) -> Result<(), String> { Ok(()) }
+/// #
+/// #[serbia]
+/// #[derive(Serialize, Deserialize)]
+/// struct S {
+///     #[serbia(validate_element = "palette_index")]
+///     pixels: [u8; 256],
+///     #[serbia(validate = "normalized")]
+///     weights: [f32; 64],
+/// }
+/// ```
+///
+/// ## Recovering from invalid elements
+///
+/// With `#[serbia(on_element_error = "default")]`, elements that fail to deserialize
+/// are replaced with `Default::default()` instead of failing the whole thing, in
+/// arrays of any length.
+/// To find out which elements were replaced, add
+/// `#[serbia(element_error_hook = "path")]`. The function is called with the index of
+/// each replaced element and a `&dyn Display` of its error.
+///
+/// Each element is read in full before it's deserialized, so that a bad element
+/// doesn't leave the input halfway through it. That only works in self-describing
+/// formats, so elements are only recovered in human readable ones like JSON or YAML.
+/// In binary formats like bincode, an invalid element still fails deserialization,
+/// with an error saying it couldn't be replaced, just like input that's broken
+/// altogether does in any format. Which format is used is only known at runtime, so
+/// this can't be caught at compile time.
+///
+/// ```rust
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// # #[derive(Serialize, Deserialize, Default)]
+/// # struct Setting;
+/// # fn warn_user(index: usize, err: &dyn std::fmt::Display) {}
+/// #
+/// #[serbia]
+/// #[derive(Serialize, Deserialize)]
+/// struct S {
+///     #[serbia(on_element_error = "default", element_error_hook = "warn_user")]
+///     settings: [Setting; 128],
+/// }
+/// ```
+///
+/// ## Delta encoding
+///
+/// Arrays of primitive integers that are close to monotonic (timestamps, offsets)
+/// can be serialized as the first element followed by differences between
+/// neighbors with `#[serbia(delta)]`. This is a lot more compact in formats with
+/// variable-length integers. Deltas of signed types are zigzag encoded.
+/// Deltas of unsigned types can't be negative, so serializing a decreasing
+/// array of unsigned integers fails, unless it's opted into with
+/// `#[serbia(delta_zigzag)]`. That zigzag encodes their deltas too, which makes
+/// increasing arrays take more space. Deltas have to fit in an integer of the
+/// element type's width, and elements that overflow fail to deserialize.
+///
+/// ```rust
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// #
+/// #[serbia]
+/// #[derive(Serialize, Deserialize)]
+/// struct S {
+///     #[serbia(delta)]
+///     timestamps: [u64; 1000],
+///     #[serbia(delta)]
+///     offsets: [i32; 256],
+/// }
+/// ```
+///
+/// ## Packed encoding
+///
+/// Arrays of fixed-width primitive numbers (`u8` to `u128`, `i8` to `i128`, `f32`, `f64`)
+/// can be serialized as a single byte string of little-endian or big-endian values
+/// with `#[serbia(packed = "le")]` or `#[serbia(packed = "be")]`. In binary formats
+/// like bincode the output is then the same as the array's memory layout on
+/// a platform with that byte order.
+///
+/// ```rust
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// #
+/// #[serbia]
+/// #[derive(Serialize, Deserialize)]
+/// struct S {
+///     #[serbia(packed = "le")]
+///     samples: [f32; 1024],
+///     #[serbia(packed = "be")]
+///     registers: [u32; 256],
+/// }
+/// ```
+///
+/// ## Text buffers
+///
+/// Byte buffers holding NUL-padded text, common in structs mirroring C ones, can be
+/// serialized as strings with `#[serbia(cstr)]` or `#[serbia(utf8)]`. The string
+/// ends at the first NUL. `cstr` requires the buffer to contain a terminating NUL,
+/// so it can hold one byte less than `utf8`. Element types `u8`, `i8` and `c_char`
+/// are supported. Strings that don't fit in the buffer or aren't valid UTF-8 are
+/// rejected.
+///
+/// ```rust
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// # use std::os::raw::c_char;
+/// #
+/// #[serbia]
+/// #[derive(Serialize, Deserialize)]
+/// struct S {
+///     #[serbia(cstr)]
+///     name: [c_char; 64],
+///     #[serbia(utf8)]
+///     label: [u8; 256],
+/// }
+/// ```
+///
+/// ## Rows
+///
+/// Big arrays can be made more readable in human-readable formats like YAML by
+/// splitting them into rows with `#[serbia(rows = ...)]`, like a hexdump. The last
+/// row may be shorter than the others. In binary formats the array is serialized
+/// as usual, unless `#[serbia(rows_in_binary)]` is also given.
+///
+/// ```rust
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// #
+/// #[serbia]
+/// #[derive(Serialize, Deserialize)]
+/// struct S {
+///     #[serbia(rows = 16)]
+///     dump: [u8; 4096],
+/// }
+/// ```
+///
+/// ## Delimited strings
+///
+/// Sources like environment variables can't express sequences. With
+/// `#[serbia(delimited = ",")]` an array is serialized as a single string of elements
+/// separated by the given delimiter, using the elements' `Display` and `FromStr`
+/// implementations. Whitespace around elements is ignored when deserializing.
+///
+/// ```rust
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// #
+/// #[serbia]
+/// #[derive(Serialize, Deserialize)]
+/// struct S {
+///     #[serbia(delimited = ",")]
+///     ports: [u16; 40],  // "8000,8001,8002,..."
+/// }
+/// ```
+///
+/// ## Custom codecs
+///
+/// For encodings *Serbia* doesn't know about, implement the `ArrayCodec` trait
+/// from the [serbia-codec](https://docs.rs/serbia-codec) crate and point a field at
+/// your implementation with `#[serbia(codec = "...")]`. The codec gets the whole
+/// array and a `Serializer`, or a `Deserializer` to build the array from.
+/// `serbia_codec::Tuple` encodes arrays the way *Serbia* does by default, which
+/// is handy for codecs that only change the encoding in some formats.
+///
+/// ```rust
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// # use serbia_codec::ArrayCodec;
+/// # struct Hex;
+/// # impl<const N: usize> ArrayCodec<u8, N> for Hex {
+/// #     fn serialize<S: serde::Serializer>(array: &[u8; N], serializer: S) -> Result<S::Ok, S::Error> {
+/// #         unimplemented!()
+/// #     }
+/// #     fn deserialize<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<[u8; N], D::Error> {
+/// #         unimplemented!()
+/// #     }
+/// # }
+/// #
+/// #[serbia]
+/// #[derive(Serialize, Deserialize)]
+/// struct S {
+///     #[serbia(codec = "Hex")]  // Hex implements serbia_codec::ArrayCodec
+///     key: [u8; 64],
+/// }
+/// ```
+///
+/// ## Safe code generation
+///
+/// By default, *Serbia* deserializes arrays in place with a bit of `unsafe` code.
+/// If you'd rather avoid that, `#[serbia(safe)]` collects the elements in a `Vec`
+/// first and converts it into an array afterwards. This costs an extra heap
+/// allocation per array.
+///
+/// Enabling the `safe` cargo feature does the same for every field, except the ones
+/// with `#[serbia(zeroize)]`.
+///
+/// ```rust
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// #
+/// #[serbia]
+/// #[derive(Serialize, Deserialize)]
+/// struct S {
+///     #[serbia(safe)]
+///     arr: [String; 64],
+/// }
+/// ```
+///
+/// ## Wiping secrets
+///
+/// `#[serbia(zeroize)]` wipes the buffer an array is deserialized into, both when
+/// deserialization fails partway and after the finished array is moved out of it.
+/// If the array fails `serbia(validate)`, it's wiped too. Elements that are thrown
+/// away are wiped with their own `Zeroize` implementation, so that whatever they own is
+/// wiped as well, and the element type has to implement `zeroize::Zeroize`. This is
+/// meant for key material and the like. It applies to arrays of any length and needs
+/// the [zeroize](https://docs.rs/zeroize) crate among your dependencies.
+///
+/// This only works with the default encoding and can't be combined with
+/// `#[serbia(safe)]`. Copies made by the deserializer itself, like the input buffer, are
+/// out of *Serbia*'s reach.
+///
+/// ```rust
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// #
+/// #[serbia]
+/// #[derive(Serialize, Deserialize)]
+/// struct Keys {
+///     #[serbia(zeroize)]
+///     secret: [u8; 32],
+///     #[serbia(zeroize)]
+///     scalars: [u64; 40],
+/// }
+/// ```
+///
+/// ## Huge arrays
+///
+/// Arrays bigger than 16 KiB are collected on the heap while being deserialized, so
+/// *Serbia* doesn't keep a copy of its own on the stack. Array lengths can be any
+/// constant expression.
+///
+/// That doesn't make arrays held by value fit on a small stack, though. Serde's
+/// derived code still moves the finished array around by value, a few times over in
+/// debug builds, so a `[u8; 1 << 20]` field overflows the default 2 MiB stack of
+/// a spawned thread regardless. Put arrays that big in a `Box<[T; N]>` instead, as
+/// described below.
+///
+/// ```rust
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// #
+/// #[serbia]
+/// #[derive(Serialize, Deserialize)]
+/// struct S {
+///     samples: [f64; 1 << 16],
+/// }
+/// ```
+///
+/// ## Arrays in smart pointers
+///
+/// Fields of type `Box<[T; N]>`, `Rc<[T; N]>` or `Arc<[T; N]>` are supported too.
+/// Their arrays are deserialized straight into heap memory and serialized by
+/// reference, so they never end up on the stack. This is the way to go for arrays
+/// too big for the stack.
+///
+/// These can't be combined with a custom encoding or `serbia(zeroize)`.
+///
+/// ```rust
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// #
+/// #[serbia]
+/// #[derive(Serialize, Deserialize)]
+/// struct S {
+///     frame: Box<[u8; 16_777_216]>,
+/// }
+/// ```
+///
+/// ## Borrowed byte arrays
+///
+/// Fields of type `&'a [u8; N]` borrow their bytes straight from the input, of any
+/// length. They're serialized as a byte string, so this works with formats that
+/// hand out borrowed bytes, like bincode or postcard, but not JSON. *Serbia* adds
+/// `#[serde(borrow)]` to such fields for you.
+///
+/// References to arrays of other element types can only be serialized. Byte arrays
+/// *Serde* can handle by itself are left alone in types that are only serialized, so
+/// that their format doesn't change.
+///
+/// ```rust
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// #
+/// #[serbia]
+/// #[derive(Serialize, Deserialize)]
+/// struct Message<'a> {
+///     id: u32,
+///     payload: &'a [u8; 4096],
+/// }
+/// ```
+///
+/// ## Borrowed elements
+///
+/// Elements can borrow from the input too, like `[&'a str; N]`. Fields whose type
+/// contains a reference get `#[serde(borrow)]` added for you. Like in *Serde*,
+/// other types with lifetimes, like `Cow<'a, str>`, only borrow if you add
+/// `#[serde(borrow)]` yourself. Elements of type `Cow<'a, str>` and `Cow<'a, [u8]>`
+/// are then borrowed whenever the format allows.
+///
+/// ```rust
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// use std::borrow::Cow;
+/// #
+/// #[serbia]
+/// #[derive(Serialize, Deserialize)]
+/// struct S<'a> {
+///     names: [&'a str; 40],
+///     #[serde(borrow)]
+///     labels: [Cow<'a, str>; 40],
+/// }
+/// ```
+///
+/// ## Big tuples
+///
+/// *Serde* only handles tuples of up to 16 elements. Longer ones are (de)serialized
+/// as tuples by *Serbia*, in structs, tuple structs and enum variants alike. Options
+/// for array elements don't apply to them.
+///
+/// ```rust
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// #
+/// #[serbia]
+/// #[derive(Serialize, Deserialize)]
+/// struct Row(
+///     (u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8),
+/// );
+/// ```
+///
+/// ## Default values
+///
+/// `#[derive(Default)]` doesn't work for arrays longer than 32 elements either.
+/// *Serbia* replaces it with an impl that builds arrays element by element, so
+/// container-level `#[serde(default)]` works too. Bounds are only put on what the
+/// fields need. A `Default` derived in a `cfg_attr` gets an impl under the same
+/// predicate, and fields behind `#[cfg]` are only initialized when they're there.
+///
+/// ```rust
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// #
+/// #[serbia]
+/// #[derive(Serialize, Deserialize, Default)]
+/// #[serde(default)]
+/// struct S<T> {
+///     arr: [T; 64],
+///     name: String,
+/// }
+/// ```
+///
+/// ## Default fields
+///
+/// A bare `#[serde(default)]` on a big array is pointed at a generated function
+/// that fills the array with `Default::default()`. A different fill value can be
+/// given with `#[serbia(default = ...)]`, which also makes the field optional.
+/// Strings are parsed as expressions.
+///
+/// ```rust
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// #
+/// #[serbia]
+/// #[derive(Serialize, Deserialize)]
+/// struct S {
+///     #[serde(default)]
+///     zeros: [u8; 300],
+///     #[serbia(default = 0xFF)]
+///     erased: [u8; 300],
+///     #[serbia(default = "u16::MAX")]
+///     maxed: [u16; 300],
+/// }
+/// ```
+///
+/// ## Conditional derives
+///
+/// Derives made conditional with `cfg_attr`, like when *Serde* is an optional
+/// feature, are picked up too. Whatever *Serbia* generates is then conditional on
+/// the same predicate, and on the `cfg` attributes of the field it's for.
+///
+/// ```rust
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// #
+/// #[serbia]
+/// #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// struct S {
+///     arr: [u8; 300],
+/// }
+/// ```
+///
+/// ## Container options
+///
+/// `#[serbia(...)]` on the type itself takes options for all of its fields.
+/// `threshold = N` handles every array longer than `N` elements, not just the ones
+/// *Serde* can't, and `all` handles arrays of any length. `serialize` and
+/// `deserialize` generate code even if the derives aren't recognized, like when
+/// they're renamed. Any other field option becomes the default for every big array
+/// field. A field's own options override it, and `#[serbia(tuple)]` goes back to
+/// the plain encoding.
+///
+/// ```rust
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// #
+/// #[serbia(threshold = 16, delta)]
+/// #[derive(Serialize, Deserialize)]
+/// struct S {
+///     timestamps: [u64; 20],
+///     #[serbia(tuple)]
+///     names: [String; 40],
+/// }
+/// ```
+///
+/// ## Type aliases
+///
+/// Instead of putting `#[serbia(bufsize = ...)]` on every field whose type is an
+/// alias, the aliases can be declared once on the container. Lengths are given the
+/// same way as for `bufsize`. *Serbia* checks at compile time that each alias
+/// really is an array of that length. Arrays in an `Option`, like `Option<Key>`,
+/// are handled too, as long as they're not generic or borrowed. Like with plain
+/// *Serde*, a missing `Option` deserializes to `None`.
+///
+/// ```rust
+/// # use serbia::serbia;
+/// # use serde::{Serialize, Deserialize};
+/// const BLOCK_LEN: usize = 48;
+/// type Key = [u8; 64];
+/// type Block = [u32; BLOCK_LEN];
+/// #
+/// #[serbia(alias(Key = 64, Block = "BLOCK_LEN"))]
+/// #[derive(Serialize, Deserialize)]
+/// struct S {
+///     key: Key,
+///     backup_key: Option<Key>,
+///     blocks: Block,
+/// }
+/// ```
+///
 /// ## Interaction with Serde field attributes
 /// *Serbia* detects when certain *Serde* field attributes are used and avoids
 /// generating code that would cause a conflict, instead yielding to *Serde*.
@@ -253,18 +687,16 @@ fn render_deserialize_fn(fn_ident: &Ident, len: impl ToTokens) -> TokenStream {
 /// #         where
 /// #             A: serde::de::SeqAccess<'de>,
 /// #         {
-/// #             unsafe {
-/// #                 let mut arr: Self::Value = std::mem::MaybeUninit::uninit().assume_init();
+/// #             let mut arr: Self::Value = [0; 42];
 /// #
-/// #                 for (i, v) in arr.iter_mut().enumerate() {
-/// #                     *v = match seq.next_element()? {
-/// #                         Some(val) => val,
-/// #                         None => return Err(serde::de::Error::invalid_length(i, &self)),
-/// #                     };
-/// #                 }
-/// #
-/// #                 Ok(arr)
+/// #             for (i, v) in arr.iter_mut().enumerate() {
+/// #                 *v = match seq.next_element()? {
+/// #                     Some(val) => val,
+/// #                     None => return Err(serde::de::Error::invalid_length(i, &self)),
+/// #                 };
 /// #             }
+/// #
+/// #             Ok(arr)
 /// #         }
 /// #     }
 /// #
@@ -285,7 +717,7 @@ fn render_deserialize_fn(fn_ident: &Ident, len: impl ToTokens) -> TokenStream {
 /// #[serbia]
 /// #[derive(Debug, Serialize, Deserialize, PartialEq)]
 /// struct S {
-///     big_arr: Option<[u8; 300]>,  // no code generated for this nested array
+///     big_arrs: Vec<[u8; 300]>,  // no code generated for this nested array
 /// }
 /// ```
 ///
@@ -293,69 +725,229 @@ fn render_deserialize_fn(fn_ident: &Ident, len: impl ToTokens) -> TokenStream {
 /// so there might be conflicts there. This can probably be worked around by using
 /// `#[serbia(skip)]` on each field that *Serbia* would try to generate custom
 /// (de)serialization code for.
+///
+/// In-place deserialization. *Serde*'s derived `deserialize_in_place` assigns fields
+/// with `deserialize_with` by value, and there's no attribute to hand it an in-place
+/// variant instead. Big arrays are deserialized into a fresh array and moved into
+/// place, so the allocations of their elements aren't reused. Hand-written decoding
+/// loops can use `serbia_codec::deserialize_in_place` to overwrite an existing array
+/// element by element instead.
 #[proc_macro_attribute]
 pub fn serbia(
-    _attr: proc_macro::TokenStream,
+    attr: proc_macro::TokenStream,
     input: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
+    let args = parse_macro_input!(attr as ContainerArgs);
     let mut input = parse_macro_input!(input as Item);
-    let context = input.context();
+    let mut context = input.context();
+    context.serialize |= args.serialize;
+    context.deserialize |= args.deserialize;
 
     let mut fn_defs = vec![];
+    let default_impl = input.take_default_derive();
+    let lenient_mod = format_ident!("serbia_lenient_{}", context.type_name);
+    let mut lenient_cfgs = vec![];
 
-    for (i, field) in input.big_array_fields().enumerate() {
-        let mut generate_bounds_for = None;
+    for (i, mut field) in input
+        .big_array_fields(&args, context.deserialize)
+        .enumerate()
+    {
+        // Generic element types need bounds, since Serde doesn't infer any for fields
+        // with `serialize_with` or `deserialize_with`. Big tuples can have several.
+        let element_types = match &field.tuple_types {
+            Some(tuple_types) => tuple_types.iter().collect(),
+            None => field.element_type.iter().collect::<Vec<_>>(),
+        };
+        let generate_bounds_for: Vec<_> = context
+            .generics
+            .type_params()
+            .filter(|param| {
+                element_types.iter().any(|el_ty| match el_ty {
+                    Type::Path(TypePath { path, .. }) => path.is_ident(&param.ident),
+                    _ => false,
+                })
+            })
+            .collect();
 
-        if let Some(Type::Path(TypePath { path: el_ty, .. })) = field.element_type {
-            if let Some(el_ty) = el_ty.get_ident() {
-                generate_bounds_for = context.generics.type_params().find(|ty| &ty.ident == el_ty);
+        // Helpers are only compiled along with the derive that uses them, and the field.
+        let field_cfg = field_cfg(field.field);
+        let serialize_fn_cfg = cfg_all(
+            field_cfg
+                .iter()
+                .chain(&context.serialize_cfg)
+                .cloned()
+                .collect(),
+        );
+        let deserialize_fn_cfg = cfg_all(
+            field_cfg
+                .iter()
+                .chain(&context.deserialize_cfg)
+                .cloned()
+                .collect(),
+        );
+
+        // A declared length is checked against the type, and the helpers take the real
+        // length from the type instead, so that a wrong one fails nothing but the check.
+        // Generic types can't be checked outside of the type's impls.
+        if (field.bufsize.is_some() || field.aliased)
+            && field.pointer.is_none()
+            && field.tuple_types.is_none()
+            && type_params(&field.ty, &context.generics).is_empty()
+            && lifetimes(&field.ty).is_empty()
+        {
+            // Aliases are checked once, on the container.
+            if let Some(span) = field.bufsize {
+                let what = format!("bufsize = {} but the field type", field.len);
+                fn_defs.push(gated(
+                    render_len_check(&field.ty, &field.len, span, &what),
+                    &field_cfg,
+                ));
             }
+            let len_ident = format_ident!("SERBIA_LEN_{}_ARR_{}", context.type_name, i);
+            fn_defs.push(gated(render_len_const(&len_ident, &field.ty), &field_cfg));
+            field.len = quote!(#len_ident);
+        }
+
+        if let Some(check) = render_rows_check(&field) {
+            fn_defs.push(gated(check, &field_cfg));
+        }
+
+        // Options are wrapped in a helper type, which can't refer to generic parameters.
+        if field.optional
+            && ((context.serialize && field.serialize)
+                || (context.deserialize && field.deserialize))
+            && !(type_params(&field.ty, &context.generics).is_empty()
+                && lifetimes(&field.ty).is_empty())
+        {
+            panic!("arrays in an Option can't be generic or borrowed");
         }
 
         if context.serialize && field.serialize {
             let fn_ident = format_ident!("serbia_serialize_{}_arr_{}", context.type_name, i);
             let fn_name = fn_ident.to_string();
 
-            field.field.attrs.push(parse_quote! {
-                #[serde(serialize_with = #fn_name)]
-            });
-            if let Some(type_param) = generate_bounds_for {
-                let bound: syn::TypeParam = parse_quote! {
-                    #type_param: Serialize
-                };
-                let bound = bound.into_token_stream().to_string();
+            field.field.attrs.push(serde_attr(
+                quote!(serialize_with = #fn_name),
+                &context.serialize_cfg,
+            ));
+            if !generate_bounds_for.is_empty() {
+                let bound = generate_bounds_for
+                    .iter()
+                    .map(|param| serialize_bounds(&field, &param.ident).to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
 
-                field.field.attrs.push(parse_quote! {
-                    #[serde(bound(serialize = #bound))]
-                });
+                field.field.attrs.push(serde_attr(
+                    quote!(bound(serialize = #bound)),
+                    &context.serialize_cfg,
+                ));
             }
 
-            fn_defs.push(render_serialize_fn(&fn_ident, &field.len));
+            fn_defs.push(gated(
+                render_serialize_fn(&fn_ident, &field),
+                &serialize_fn_cfg,
+            ));
         }
         if context.deserialize && field.deserialize {
             let fn_ident = format_ident!("serbia_deserialize_{}_arr_{}", context.type_name, i);
             let fn_name = fn_ident.to_string();
 
-            field.field.attrs.push(parse_quote! {
-                #[serde(deserialize_with = #fn_name)]
-            });
-            if let Some(type_param) = generate_bounds_for {
-                let bound: syn::TypeParam = parse_quote! {
-                    #type_param: for<'d> Deserialize<'d>
-                };
-                let bound = bound.into_token_stream().to_string();
+            field.field.attrs.push(serde_attr(
+                quote!(deserialize_with = #fn_name),
+                &context.deserialize_cfg,
+            ));
+            // References can't be deserialized without borrowing, so there's no point
+            // in making users ask for it.
+            if !field.borrow && contains_reference(&field.field.ty) {
+                field
+                    .field
+                    .attrs
+                    .push(serde_attr(quote!(borrow), &context.deserialize_cfg));
+                field.borrow = true;
+            }
+            if field.default_none {
+                field
+                    .field
+                    .attrs
+                    .push(serde_attr(quote!(default), &context.deserialize_cfg));
+            }
+            if !generate_bounds_for.is_empty() {
+                let bound = generate_bounds_for
+                    .iter()
+                    .map(|param| deserialize_bounds(&field, &param.ident).to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
 
-                field.field.attrs.push(parse_quote! {
-                    #[serde(bound(deserialize = #bound))]
-                });
+                field.field.attrs.push(serde_attr(
+                    quote!(bound(deserialize = #bound)),
+                    &context.deserialize_cfg,
+                ));
             }
 
-            fn_defs.push(render_deserialize_fn(&fn_ident, &field.len));
+            if field.default_on_element_error {
+                field.lenient_mod = Some(lenient_mod.clone());
+                lenient_cfgs.push(deserialize_fn_cfg.clone());
+            }
+
+            fn_defs.push(gated(
+                render_deserialize_fn(&fn_ident, &field),
+                &deserialize_fn_cfg,
+            ));
+        }
+        if context.deserialize && field.default {
+            let fn_ident = format_ident!("serbia_default_{}_arr_{}", context.type_name, i);
+            let fn_name = fn_ident.to_string();
+
+            if !field.point_default_at(&fn_name) {
+                field.field.attrs.push(serde_attr(
+                    quote!(default = #fn_name),
+                    &context.deserialize_cfg,
+                ));
+            }
+            // Fields that are deserialized have this covered by their deserialize bounds.
+            if !field.deserialize
+                && field.default_value.is_none()
+                && !generate_bounds_for.is_empty()
+            {
+                let bound = generate_bounds_for
+                    .iter()
+                    .map(|param| quote!(#param: core::default::Default).to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                field.field.attrs.push(serde_attr(
+                    quote!(bound(deserialize = #bound)),
+                    &context.deserialize_cfg,
+                ));
+            }
+            fn_defs.push(gated(
+                render_default_fn(&fn_ident, &field, &context.generics),
+                &deserialize_fn_cfg,
+            ));
         }
     }
 
+    // Fields recovering from invalid elements share one module, compiled along with
+    // any of their helpers.
+    let lenient_mod = if lenient_cfgs.is_empty() {
+        None
+    } else if lenient_cfgs.iter().any(Option::is_none) {
+        Some(render_lenient_mod(&lenient_mod))
+    } else {
+        let preds = lenient_cfgs.iter().flatten();
+        Some(gated(
+            render_lenient_mod(&lenient_mod),
+            &Some(quote! { any(#(#preds),*) }),
+        ))
+    };
+
+    let alias_checks = args.render_alias_checks();
+
     let expanded = quote! {
         #input
+        #default_impl
+        #alias_checks
+        #lenient_mod
         #(#fn_defs)*
     };
 
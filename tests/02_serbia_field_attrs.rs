@@ -51,3 +51,540 @@ fn skip_field() {
 
     assert_eq!(original, deserialized);
 }
+
+mod u64_as_string {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(n: &u64, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(n)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<u64, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+#[test]
+fn element_with() {
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        #[serbia(element_with = "u64_as_string")]
+        ids: [u64; 40],
+    }
+
+    let mut original = S { ids: [0; 40] };
+    original.ids[1] = u64::MAX;
+
+    let serialized = serde_json::to_value(&original).unwrap();
+    assert_eq!(serialized["ids"][0], "0");
+    assert_eq!(serialized["ids"][1], "18446744073709551615");
+
+    let deserialized = serde_json::from_value(serialized).unwrap();
+    assert_eq!(original, deserialized);
+}
+
+#[test]
+fn element_with_small_array() {
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        #[serbia(element_with = "u64_as_string")]
+        ids: [u64; 8],
+    }
+
+    let original = S { ids: [9; 8] };
+
+    let serialized = serde_json::to_value(&original).unwrap();
+    assert_eq!(serialized["ids"][7], "9");
+
+    let deserialized = serde_json::from_value(serialized).unwrap();
+    assert_eq!(original, deserialized);
+}
+
+#[test]
+fn element_with_foreign_type() {
+    // Doesn't implement Serialize or Deserialize.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Day(u16);
+
+    fn ser_day<S>(day: &Day, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        day.0.serialize(serializer)
+    }
+
+    fn de_day<'de, D>(deserializer: D) -> Result<Day, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        u16::deserialize(deserializer).map(Day)
+    }
+
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        #[serbia(element_serialize_with = "ser_day")]
+        #[serbia(element_deserialize_with = "de_day")]
+        days: [Day; 64],
+    }
+
+    let original = S { days: [Day(3); 64] };
+
+    let serialized = serde_yaml::to_string(&original).unwrap();
+    let deserialized = serde_yaml::from_str(&serialized).unwrap();
+
+    assert_eq!(original, deserialized);
+}
+
+#[test]
+fn element_serialize_with_only() {
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        #[serbia(element_serialize_with = "u64_as_string::serialize")]
+        ids: [u64; 40],
+    }
+
+    let original = S { ids: [7; 40] };
+
+    let serialized = serde_json::to_value(&original).unwrap();
+    assert_eq!(serialized["ids"][0], "7");
+
+    let deserialized: S =
+        serde_json::from_value(serde_json::json!({ "ids": vec![7; 40] })).unwrap();
+    assert_eq!(original, deserialized);
+}
+
+#[test]
+fn validate_element() {
+    fn palette_index(_: usize, index: &u8) -> Result<(), String> {
+        if *index < 16 {
+            Ok(())
+        } else {
+            Err(format!("{} is not a palette index", index))
+        }
+    }
+
+    type Weights = [f32; 64];
+
+    fn finite(_: usize, w: &f32) -> Result<(), &'static str> {
+        if w.is_finite() {
+            Ok(())
+        } else {
+            Err("weight isn't finite")
+        }
+    }
+
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        #[serbia(validate_element = "palette_index")]
+        pixels: [u8; 256],
+        #[serbia(bufsize = 64, validate_element = "finite")]
+        weights: Weights,
+    }
+
+    let mut original = S {
+        pixels: [3; 256],
+        weights: [0.5; 64],
+    };
+
+    let serialized = serde_json::to_string(&original).unwrap();
+    let deserialized = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(original, deserialized);
+
+    original.pixels[183] = 16;
+    let serialized = serde_json::to_string(&original).unwrap();
+    let err = serde_json::from_str::<S>(&serialized)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("invalid element at index 183: 16 is not a palette index"),
+        "{}",
+        err
+    );
+
+    original.pixels[183] = 15;
+    original.weights[7] = f32::INFINITY;
+    let serialized = serde_yaml::to_string(&original).unwrap();
+    let err = serde_yaml::from_str::<S>(&serialized)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("invalid element at index 7: weight isn't finite"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn validate() {
+    fn sorted(arr: &[u32; 40]) -> Result<(), &'static str> {
+        if arr.windows(2).all(|w| w[0] <= w[1]) {
+            Ok(())
+        } else {
+            Err("not sorted")
+        }
+    }
+
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        #[serbia(validate = "sorted")]
+        a: [u32; 40],
+        #[serbia(delimited = ",", validate = "sorted")]
+        b: [u32; 40],
+    }
+
+    let mut original = S {
+        a: [1; 40],
+        b: [2; 40],
+    };
+
+    let serialized = serde_json::to_string(&original).unwrap();
+    let deserialized = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(original, deserialized);
+
+    original.b[0] = 3;
+    let serialized = serde_json::to_string(&original).unwrap();
+    let err = serde_json::from_str::<S>(&serialized)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("invalid array: not sorted"), "{}", err);
+}
+
+#[test]
+fn validate_small_array() {
+    fn reject(_: &[u8; 8]) -> Result<(), &'static str> {
+        Err("rejected")
+    }
+
+    fn below_10(_: usize, e: &u8) -> Result<(), &'static str> {
+        if *e < 10 {
+            Ok(())
+        } else {
+            Err("too big")
+        }
+    }
+
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        #[serbia(validate = "reject")]
+        a: [u8; 8],
+    }
+
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct T {
+        #[serbia(validate_element = "below_10")]
+        a: [u8; 8],
+    }
+
+    let err = serde_json::from_str::<S>(r#"{"a":[0,0,0,0,0,0,0,0]}"#)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("invalid array: rejected"), "{}", err);
+
+    let t: T = serde_json::from_str(r#"{"a":[9,0,0,0,0,0,0,0]}"#).unwrap();
+    assert_eq!(t.a[0], 9);
+    let err = serde_json::from_str::<T>(r#"{"a":[0,0,10,0,0,0,0,0]}"#)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("invalid element at index 2: too big"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn default_on_element_error() {
+    use std::sync::Mutex;
+
+    static RECOVERED: Mutex<Vec<(usize, String)>> = Mutex::new(Vec::new());
+
+    fn warn(index: usize, err: &dyn std::fmt::Display) {
+        RECOVERED.lock().unwrap().push((index, err.to_string()));
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Default)]
+    enum Setting {
+        #[default]
+        Off,
+        On,
+    }
+
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S<T> {
+        #[serbia(on_element_error = "default", element_error_hook = "warn")]
+        settings: [Setting; 40],
+        #[serbia(on_element_error = "default")]
+        generic: [T; 40],
+    }
+
+    let mut settings = vec![serde_json::json!("On"); 40];
+    settings[3] = serde_json::json!("Maybe");
+    settings[39] = serde_json::json!(12);
+    let mut generic = vec![serde_json::json!(5); 40];
+    generic[0] = serde_json::json!(-1);
+
+    let deserialized: S<u32> = serde_json::from_value(serde_json::json!({
+        "settings": settings,
+        "generic": generic,
+    }))
+    .unwrap();
+
+    assert_eq!(deserialized.settings[2], Setting::On);
+    assert_eq!(deserialized.settings[3], Setting::Off);
+    assert_eq!(deserialized.settings[39], Setting::Off);
+    assert_eq!(deserialized.generic[0], 0);
+    assert_eq!(deserialized.generic[1], 5);
+
+    let recovered = RECOVERED.lock().unwrap();
+    assert_eq!(recovered.len(), 2);
+    assert_eq!(recovered[0].0, 3);
+    assert!(recovered[0].1.contains("Maybe"), "{}", recovered[0].1);
+    assert_eq!(recovered[1].0, 39);
+
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Small {
+        #[serbia(on_element_error = "default")]
+        a: [u8; 8],
+    }
+
+    let small: Small = serde_json::from_str(r#"{"a":[1,2,-3,4,5,6,7,8]}"#).unwrap();
+    assert_eq!(small.a, [1, 2, 0, 4, 5, 6, 7, 8]);
+
+    // Missing elements are still an error.
+    let deserialized: Result<S<u32>, _> = serde_json::from_value(serde_json::json!({
+        "settings": ["On"],
+        "generic": generic,
+    }));
+    assert!(deserialized.is_err());
+}
+
+#[test]
+fn default_on_element_error_streaming() {
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Default)]
+    struct Setting {
+        a: u32,
+        b: u32,
+    }
+
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        #[serbia(on_element_error = "default")]
+        settings: [Setting; 40],
+    }
+
+    let mut settings = vec![r#"{"a":1,"b":2}"#; 40];
+    settings[4] = r#"{"a":"x","b":2}"#;
+    let json = format!(r#"{{"settings":[{}]}}"#, settings.join(","));
+
+    let deserialized: S = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized.settings[3], Setting { a: 1, b: 2 });
+    assert_eq!(deserialized.settings[4], Setting::default());
+    assert_eq!(deserialized.settings[5], Setting { a: 1, b: 2 });
+
+    // Broken input is still an error, rather than a bad element.
+    let broken = json.replacen(r#"{"a":"x","b":2}"#, r#"{"a":"x","b":2"#, 1);
+    let err = serde_json::from_str::<S>(&broken).unwrap_err().to_string();
+    assert!(
+        err.starts_with("invalid value at index 4 of array of length 40: key must be a string"),
+        "{}",
+        err
+    );
+
+    // Binary formats can't skip a bad element, so they say so instead.
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Flags {
+        #[serbia(on_element_error = "default")]
+        flags: [bool; 40],
+        #[serbia(on_element_error = "default")]
+        more_flags: [bool; 40],
+    }
+
+    let mut bin = vec![1; 80];
+    assert_eq!(
+        bincode::deserialize::<Flags>(&bin).unwrap(),
+        Flags {
+            flags: [true; 40],
+            more_flags: [true; 40]
+        }
+    );
+    bin[44] = 2;
+    let err = bincode::deserialize::<Flags>(&bin).unwrap_err().to_string();
+    assert!(
+        err.contains("only replaced with defaults in human readable formats"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn zeroize() {
+    fn nonzero_sum(arr: &[u64; 40]) -> Result<(), &'static str> {
+        if arr.iter().sum::<u64>() == 0 {
+            Err("all zero")
+        } else {
+            Ok(())
+        }
+    }
+
+    #[serbia]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct S<T> {
+        #[serbia(zeroize)]
+        key: [u8; 32],
+        #[serbia(zeroize, validate = "nonzero_sum")]
+        scalars: [u64; 40],
+        #[serbia(zeroize, validate_element = "not_max")]
+        bytes: [u8; 40],
+        #[serbia(zeroize)]
+        generic: [T; 40],
+    }
+
+    fn not_max(_: usize, e: &u8) -> Result<(), &'static str> {
+        if *e == u8::MAX {
+            Err("max")
+        } else {
+            Ok(())
+        }
+    }
+
+    let s = S {
+        key: [1; 32],
+        scalars: [2; 40],
+        bytes: [4; 40],
+        generic: [3u16; 40],
+    };
+    let bin = bincode::serialize(&s).unwrap();
+    assert_eq!(bincode::deserialize::<S<u16>>(&bin).unwrap(), s);
+
+    let deserialized: Result<S<u16>, _> = serde_json::from_value(serde_json::json!({
+        "key": vec![1; 32],
+        "scalars": vec![0; 40],
+        "bytes": vec![4; 40],
+        "generic": vec![3; 40],
+    }));
+    assert!(deserialized.is_err());
+
+    let deserialized: Result<S<u16>, _> = serde_json::from_value(serde_json::json!({
+        "key": vec![1; 31],
+        "scalars": vec![2; 40],
+        "bytes": vec![4; 40],
+        "generic": vec![3; 40],
+    }));
+    assert!(deserialized.is_err());
+
+    let deserialized: Result<S<u16>, _> = serde_json::from_value(serde_json::json!({
+        "key": vec![1; 32],
+        "scalars": vec![2; 40],
+        "bytes": vec![255; 40],
+        "generic": vec![3; 40],
+    }));
+    assert!(deserialized.is_err());
+}
+
+#[test]
+fn zeroize_wipes_elements() {
+    use std::cell::Cell;
+    use zeroize::Zeroize;
+
+    thread_local! {
+        static WIPED: Cell<usize> = const { Cell::new(0) };
+    }
+
+    fn wiped() -> usize {
+        WIPED.with(|wiped| wiped.replace(0))
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Secret(u64);
+
+    impl Zeroize for Secret {
+        fn zeroize(&mut self) {
+            self.0.zeroize();
+            WIPED.with(|wiped| wiped.set(wiped.get() + 1));
+        }
+    }
+
+    fn below_100(_: usize, e: &Secret) -> Result<(), &'static str> {
+        if e.0 < 100 {
+            Ok(())
+        } else {
+            Err("too big")
+        }
+    }
+
+    fn nonzero(arr: &[Secret; 40]) -> Result<(), &'static str> {
+        if arr.iter().any(|e| e.0 != 0) {
+            Ok(())
+        } else {
+            Err("all zero")
+        }
+    }
+
+    #[serbia]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct S {
+        #[serbia(zeroize)]
+        secrets: [Secret; 40],
+    }
+
+    #[serbia]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Validated {
+        #[serbia(zeroize, validate_element = "below_100")]
+        elements: [Secret; 40],
+        #[serbia(zeroize, validate = "nonzero")]
+        array: [Secret; 40],
+    }
+
+    let mut secrets = vec![serde_json::json!(1); 40];
+
+    // Finished arrays are moved out without wiping the elements.
+    let deserialized: S =
+        serde_json::from_value(serde_json::json!({ "secrets": secrets })).unwrap();
+    assert_eq!(deserialized.secrets[39], Secret(1));
+    assert_eq!(wiped(), 0);
+
+    // Elements read before a failure are wiped.
+    secrets[10] = serde_json::json!("x");
+    assert!(serde_json::from_value::<S>(serde_json::json!({ "secrets": secrets })).is_err());
+    assert_eq!(wiped(), 10);
+
+    // An element failing validation is wiped along with the ones before it.
+    secrets[10] = serde_json::json!(1);
+    secrets[5] = serde_json::json!(100);
+    let deserialized: Result<Validated, _> = serde_json::from_value(serde_json::json!({
+        "elements": secrets,
+        "array": vec![1; 40],
+    }));
+    assert!(deserialized.is_err());
+    assert_eq!(wiped(), 6);
+
+    // An array failing validation is wiped after it's moved out of the buffer.
+    let deserialized: Result<Validated, _> = serde_json::from_value(serde_json::json!({
+        "elements": vec![1; 40],
+        "array": vec![0; 40],
+    }));
+    assert!(deserialized.is_err());
+    // The elements field is dropped by Serde without being wiped.
+    assert_eq!(wiped(), 40);
+}
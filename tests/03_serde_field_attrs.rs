@@ -45,18 +45,16 @@ fn serialize_deserialize_with() {
             where
                 A: serde::de::SeqAccess<'de>,
             {
-                unsafe {
-                    let mut arr: Self::Value = std::mem::MaybeUninit::uninit().assume_init();
+                let mut arr: Self::Value = [0; 42];
 
-                    for (i, v) in arr.iter_mut().enumerate() {
-                        *v = match seq.next_element()? {
-                            Some(val) => val,
-                            None => return Err(serde::de::Error::invalid_length(i, &self)),
-                        };
-                    }
-
-                    Ok(arr)
+                for (i, v) in arr.iter_mut().enumerate() {
+                    *v = match seq.next_element()? {
+                        Some(val) => val,
+                        None => return Err(serde::de::Error::invalid_length(i, &self)),
+                    };
                 }
+
+                Ok(arr)
             }
         }
 
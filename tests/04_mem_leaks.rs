@@ -42,6 +42,7 @@ fn deserializing_failure_mem_leak() {
     }
 
     // Foo will create a strong ref to RC_STRING when deserialized into.
+    #[allow(dead_code)]
     struct Foo(Arc<String>);
 
     impl<'de> Deserialize<'de> for Foo {
@@ -92,3 +93,133 @@ fn deserializing_failure_mem_leak() {
     // If it's not, we have a memory leak.
     assert_eq!(Arc::strong_count(&RC_STRING), 1);
 }
+
+#[test]
+fn validation_failure_mem_leak() {
+    use lazy_static::lazy_static;
+    use std::sync::Arc;
+
+    lazy_static! {
+        static ref RC_STRING: Arc<String> = Arc::new("foo".to_string());
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    #[serde(from = "String")]
+    struct Foo(Arc<String>, String);
+
+    impl From<String> for Foo {
+        fn from(s: String) -> Self {
+            Foo(Arc::clone(&RC_STRING), s)
+        }
+    }
+
+    fn not_bad(_: usize, foo: &Foo) -> Result<(), &'static str> {
+        if foo.1 == "bad" {
+            Err("bad")
+        } else {
+            Ok(())
+        }
+    }
+
+    #[serbia]
+    #[derive(Deserialize)]
+    struct S {
+        #[serbia(validate_element = "not_bad")]
+        _arr_big: [Foo; 40],
+    }
+
+    let json = serde_json::json!({
+        "_arr_big": ["asd", "asd", "bad", "asd"]
+    })
+    .to_string();
+
+    let faulty_struct: Result<S, _> = serde_json::from_str(&json);
+    assert!(faulty_struct.is_err());
+
+    // The two elements deserialized before the invalid one, and the invalid one
+    // itself, should have been dropped.
+    assert_eq!(Arc::strong_count(&RC_STRING), 1);
+}
+
+#[test]
+fn element_error_mem_leak() {
+    use lazy_static::lazy_static;
+    use std::sync::Arc;
+
+    lazy_static! {
+        static ref RC_STRING: Arc<String> = Arc::new("foo".to_string());
+    }
+
+    // Foo fails to deserialize from anything but a string.
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    #[serde(from = "String")]
+    struct Foo(Arc<String>);
+
+    impl From<String> for Foo {
+        fn from(_: String) -> Self {
+            Foo(Arc::clone(&RC_STRING))
+        }
+    }
+
+    #[serbia]
+    #[derive(Deserialize)]
+    struct S {
+        _arr_big: [Foo; 40],
+    }
+
+    let json = serde_json::json!({
+        "_arr_big": ["asd", "asd", "asd", 42, "asd"]
+    })
+    .to_string();
+
+    let faulty_struct: Result<S, _> = serde_json::from_str(&json);
+    assert!(faulty_struct.is_err());
+
+    // The three elements deserialized before the error should have been dropped.
+    assert_eq!(Arc::strong_count(&RC_STRING), 1);
+}
+
+#[test]
+fn element_panic_mem_leak() {
+    use lazy_static::lazy_static;
+    use std::sync::Arc;
+
+    lazy_static! {
+        static ref RC_STRING: Arc<String> = Arc::new("foo".to_string());
+    }
+
+    // Foo panics when deserialized from "panic".
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    #[serde(from = "String")]
+    struct Foo(Arc<String>);
+
+    impl From<String> for Foo {
+        fn from(s: String) -> Self {
+            if s == "panic" {
+                panic!("deserializing a Foo from {:?}", s);
+            }
+            Foo(Arc::clone(&RC_STRING))
+        }
+    }
+
+    #[serbia]
+    #[derive(Deserialize)]
+    struct S {
+        _arr_big: [Foo; 40],
+    }
+
+    let json = serde_json::json!({
+        "_arr_big": ["asd", "asd", "asd", "panic", "asd"]
+    })
+    .to_string();
+
+    let result = std::panic::catch_unwind(|| serde_json::from_str::<S>(&json));
+    assert!(result.is_err());
+
+    // The three elements deserialized before the panic should have been dropped
+    // while unwinding.
+    assert_eq!(Arc::strong_count(&RC_STRING), 1);
+}
@@ -0,0 +1,536 @@
+use serbia::serbia;
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn delta_roundtrip() {
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        #[serbia(delta)]
+        timestamps: [u64; 300],
+        #[serbia(delta)]
+        offsets: [i32; 40],
+        #[serbia(delta)]
+        small: [i8; 4],
+    }
+
+    let mut original = S {
+        timestamps: [0; 300],
+        offsets: [0; 40],
+        small: [-100, 27, 0, -1],
+    };
+    for (i, t) in original.timestamps.iter_mut().enumerate() {
+        *t = 1_600_000_000 + i as u64 * 3;
+    }
+    for (i, o) in original.offsets.iter_mut().enumerate() {
+        *o = 100 - i as i32 * 7;
+    }
+
+    let serialized = serde_json::to_value(&original).unwrap();
+
+    assert_eq!(serialized["timestamps"][0], 1_600_000_000);
+    assert_eq!(serialized["timestamps"][1], 3);
+    assert_eq!(serialized["timestamps"][299], 3);
+    assert_eq!(serialized["offsets"][0], 100);
+    // -7 zigzag encoded
+    assert_eq!(serialized["offsets"][1], 13);
+
+    let deserialized = serde_json::from_value(serialized).unwrap();
+
+    assert_eq!(original, deserialized);
+}
+
+#[test]
+fn delta_unsigned_decreasing() {
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        #[serbia(delta)]
+        arr: [u16; 40],
+    }
+
+    let mut original = S { arr: [5; 40] };
+    original.arr[20] = 4;
+
+    assert!(serde_json::to_string(&original).is_err());
+}
+
+#[test]
+fn delta_zigzag_roundtrip() {
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        #[serbia(delta_zigzag)]
+        decreasing: [u64; 40],
+        #[serbia(delta_zigzag)]
+        extremes: [u16; 3],
+    }
+
+    let mut original = S {
+        decreasing: [5; 40],
+        extremes: [i16::MAX as u16, 0, i16::MAX as u16],
+    };
+    original.decreasing[20] = 4;
+
+    let serialized = serde_json::to_value(&original).unwrap();
+
+    // -1 and +1 zigzag encoded
+    assert_eq!(serialized["decreasing"][20], 1);
+    assert_eq!(serialized["decreasing"][21], 2);
+
+    let deserialized: S = serde_json::from_value(serialized).unwrap();
+    assert_eq!(original, deserialized);
+
+    let serialized = postcard::to_allocvec(&original).unwrap();
+    let deserialized: S = postcard::from_bytes(&serialized).unwrap();
+    assert_eq!(original, deserialized);
+
+    // The difference doesn't fit in an i16.
+    original.extremes = [u16::MAX, 0, 0];
+    let err = serde_json::to_string(&original).unwrap_err().to_string();
+    assert!(
+        err.contains("difference between elements 0 and 1 overflows i16"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn delta_overflow() {
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        #[serbia(delta)]
+        arr: [i8; 3],
+        #[serbia(delta)]
+        unsigned: [u8; 2],
+        #[serbia(delta_zigzag)]
+        zigzag: [u8; 2],
+    }
+
+    // 2 is +1 zigzag encoded
+    let deserialized: Result<S, _> =
+        serde_json::from_str(r#"{ "arr": [127, 2, 0], "unsigned": [0, 0], "zigzag": [0, 0] }"#);
+    assert!(deserialized.is_err());
+
+    let deserialized: Result<S, _> =
+        serde_json::from_str(r#"{ "arr": [126, 2, 0], "unsigned": [255, 1], "zigzag": [0, 0] }"#);
+    let err = deserialized.unwrap_err().to_string();
+    assert!(
+        err.contains("element 1 of a delta encoded array overflows u8"),
+        "{}",
+        err
+    );
+
+    // 1 is -1 zigzag encoded
+    let deserialized: Result<S, _> =
+        serde_json::from_str(r#"{ "arr": [126, 2, 0], "unsigned": [0, 0], "zigzag": [0, 1] }"#);
+    assert!(deserialized.is_err());
+
+    let deserialized: Result<S, _> =
+        serde_json::from_str(r#"{ "arr": [126, 2, 0], "unsigned": [254, 1], "zigzag": [1, 1] }"#);
+    assert_eq!(
+        deserialized.unwrap(),
+        S {
+            arr: [126, 127, 127],
+            unsigned: [254, 255],
+            zigzag: [1, 0],
+        }
+    );
+
+    // The difference doesn't fit in an i8.
+    let original = S {
+        arr: [-100, 100, 0],
+        unsigned: [0, 0],
+        zigzag: [0, 0],
+    };
+    assert!(serde_json::to_string(&original).is_err());
+}
+
+#[test]
+fn packed_roundtrip() {
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        #[serbia(packed = "le")]
+        samples: [f32; 300],
+        #[serbia(packed = "be")]
+        registers: [u32; 40],
+    }
+
+    let mut original = S {
+        samples: [0.0; 300],
+        registers: [0; 40],
+    };
+    for (i, s) in original.samples.iter_mut().enumerate() {
+        *s = i as f32 / 3.0;
+    }
+    for (i, r) in original.registers.iter_mut().enumerate() {
+        *r = 0x0102_0304 * i as u32;
+    }
+
+    let serialized = bincode::serialize(&original).unwrap();
+    let deserialized = bincode::deserialize(&serialized).unwrap();
+    assert_eq!(original, deserialized);
+
+    // bincode prefixes byte strings with a u64 length
+    assert_eq!(&serialized[0..8], &(300u64 * 4).to_le_bytes());
+    assert_eq!(&serialized[8..12], &original.samples[0].to_le_bytes());
+    assert_eq!(&serialized[12..16], &original.samples[1].to_le_bytes());
+    let registers = &serialized[8 + 300 * 4 + 8..];
+    assert_eq!(&registers[4..8], &[1, 2, 3, 4]);
+
+    let serialized = serde_json::to_string(&original).unwrap();
+    let deserialized = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(original, deserialized);
+}
+
+#[test]
+fn packed_wrong_length() {
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        #[serbia(packed = "le")]
+        arr: [u16; 4],
+    }
+
+    let deserialized: Result<S, _> = serde_json::from_str(r#"{ "arr": [1, 0, 2, 0, 3, 0, 4, 0] }"#);
+    assert_eq!(deserialized.unwrap(), S { arr: [1, 2, 3, 4] });
+
+    let deserialized: Result<S, _> = serde_json::from_str(r#"{ "arr": [1, 0, 2, 0, 3, 0, 4] }"#);
+    assert!(deserialized.is_err());
+
+    let deserialized: Result<S, _> =
+        serde_json::from_str(r#"{ "arr": [1, 0, 2, 0, 3, 0, 4, 0, 5] }"#);
+    assert!(deserialized.is_err());
+}
+
+#[test]
+fn text_roundtrip() {
+    use std::os::raw::c_char;
+
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        #[serbia(cstr)]
+        name: [c_char; 64],
+        #[serbia(utf8)]
+        label: [u8; 4],
+    }
+
+    let mut original = S {
+        name: [0; 64],
+        label: *b"full",
+    };
+    for (c, b) in original.name.iter_mut().zip("żółw".bytes()) {
+        *c = b as c_char;
+    }
+
+    let serialized = serde_json::to_string(&original).unwrap();
+    assert_eq!(serialized, r#"{"name":"żółw","label":"full"}"#);
+
+    let deserialized = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(original, deserialized);
+}
+
+#[test]
+fn text_errors() {
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        #[serbia(cstr)]
+        name: [u8; 4],
+    }
+
+    // no room for the terminating NUL
+    let deserialized: Result<S, _> = serde_json::from_str(r#"{ "name": "abcd" }"#);
+    let err = deserialized.unwrap_err().to_string();
+    assert!(err.contains("capacity 4"), "{}", err);
+
+    let deserialized: Result<S, _> = serde_json::from_str(r#"{ "name": "abc" }"#);
+    assert_eq!(deserialized.unwrap(), S { name: *b"abc\0" });
+
+    let deserialized: Result<S, _> = serde_json::from_str(r#"{ "name": "a\u0000b" }"#);
+    assert!(deserialized.is_err());
+
+    assert!(serde_json::to_string(&S { name: *b"abcd" }).is_err());
+    let err = serde_json::to_string(&S {
+        name: [0xff, 0, 0, 0],
+    })
+    .unwrap_err()
+    .to_string();
+    assert!(err.contains("capacity 4"), "{}", err);
+}
+
+#[test]
+fn rows_roundtrip() {
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        #[serbia(rows = 16)]
+        dump: [u8; 40],
+    }
+
+    let mut original = S { dump: [0; 40] };
+    for (i, b) in original.dump.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+
+    let serialized = serde_json::to_value(&original).unwrap();
+    let rows = serialized["dump"].as_array().unwrap();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0].as_array().unwrap().len(), 16);
+    assert_eq!(rows[1][0], 16);
+    assert_eq!(rows[2].as_array().unwrap().len(), 8);
+
+    let deserialized = serde_json::from_value(serialized).unwrap();
+    assert_eq!(original, deserialized);
+
+    let serialized = serde_yaml::to_string(&original).unwrap();
+    let deserialized = serde_yaml::from_str(&serialized).unwrap();
+    assert_eq!(original, deserialized);
+
+    // binary formats get a flat tuple
+    let serialized = bincode::serialize(&original).unwrap();
+    assert_eq!(serialized, original.dump);
+    let deserialized = bincode::deserialize(&serialized).unwrap();
+    assert_eq!(original, deserialized);
+}
+
+#[test]
+fn rows_in_binary() {
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        #[serbia(rows = 4, rows_in_binary)]
+        dump: [u16; 6],
+    }
+
+    let original = S {
+        dump: [1, 2, 3, 4, 5, 6],
+    };
+
+    let serialized = bincode::serialize(&original).unwrap();
+    // number of rows, then each row prefixed with its length
+    assert_eq!(serialized.len(), 8 + 8 + 4 * 2 + 8 + 2 * 2);
+    let deserialized = bincode::deserialize(&serialized).unwrap();
+    assert_eq!(original, deserialized);
+}
+
+#[test]
+fn rows_wrong_shape() {
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        #[serbia(rows = 2)]
+        arr: [u8; 5],
+    }
+
+    let deserialized: Result<S, _> = serde_json::from_str(r#"{ "arr": [[1, 2], [3, 4], [5]] }"#);
+    assert_eq!(
+        deserialized.unwrap(),
+        S {
+            arr: [1, 2, 3, 4, 5]
+        }
+    );
+
+    for json in &[
+        r#"{ "arr": [[1, 2], [3], [4, 5]] }"#,
+        r#"{ "arr": [[1, 2], [3, 4]] }"#,
+        r#"{ "arr": [[1, 2], [3, 4], [5, 6]] }"#,
+        r#"{ "arr": [[1, 2], [3, 4], [5], []] }"#,
+        r#"{ "arr": [1, 2, 3, 4, 5] }"#,
+    ] {
+        let deserialized: Result<S, _> = serde_json::from_str(json);
+        assert!(deserialized.is_err(), "{}", json);
+    }
+}
+
+#[test]
+fn delimited_roundtrip() {
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S<T> {
+        #[serbia(delimited = ",")]
+        ports: [u16; 40],
+        #[serbia(delimited = " ; ")]
+        weights: [T; 3],
+    }
+
+    let mut original = S {
+        ports: [0; 40],
+        weights: [0.5, -1.0, 2.25],
+    };
+    for (i, p) in original.ports.iter_mut().enumerate() {
+        *p = 8000 + i as u16;
+    }
+
+    let serialized = serde_json::to_value(&original).unwrap();
+    assert!(serialized["ports"]
+        .as_str()
+        .unwrap()
+        .starts_with("8000,8001,8002,"));
+    assert_eq!(serialized["weights"], "0.5 ; -1 ; 2.25");
+
+    let deserialized = serde_json::from_value(serialized).unwrap();
+    assert_eq!(original, deserialized);
+}
+
+#[test]
+fn delimited_errors() {
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        #[serbia(delimited = ",")]
+        arr: [u8; 3],
+    }
+
+    let deserialized: Result<S, _> = serde_json::from_str(r#"{ "arr": "1, 2,3" }"#);
+    assert_eq!(deserialized.unwrap(), S { arr: [1, 2, 3] });
+
+    let deserialized: Result<S, _> = serde_json::from_str(r#"{ "arr": "1,300,3" }"#);
+    let err = deserialized.unwrap_err().to_string();
+    assert!(err.contains("index 1"), "{}", err);
+    assert!(err.contains("\"300\""), "{}", err);
+
+    let deserialized: Result<S, _> = serde_json::from_str(r#"{ "arr": "1,2" }"#);
+    assert!(deserialized.is_err());
+
+    let deserialized: Result<S, _> = serde_json::from_str(r#"{ "arr": "1,2,3,4" }"#);
+    assert!(deserialized.is_err());
+}
+
+mod codecs {
+    use std::convert::TryInto;
+
+    use serbia_codec::ArrayCodec;
+    use serde::{
+        de::DeserializeOwned, de::Error, Deserialize, Deserializer, Serialize, Serializer,
+    };
+
+    pub struct Hex;
+
+    impl<const N: usize> ArrayCodec<u8, N> for Hex {
+        fn serialize<S>(array: &[u8; N], serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let hex: String = array.iter().map(|b| format!("{:02x}", b)).collect();
+            serializer.serialize_str(&hex)
+        }
+
+        fn deserialize<'de, D>(deserializer: D) -> Result<[u8; N], D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let hex = String::deserialize(deserializer)?;
+            if !hex.is_ascii() {
+                return Err(D::Error::custom("expected a hex string"));
+            }
+            if hex.len() != N * 2 {
+                return Err(D::Error::invalid_length(hex.len(), &"a hex string"));
+            }
+
+            let mut arr = [0; N];
+            for (i, b) in arr.iter_mut().enumerate() {
+                *b = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(D::Error::custom)?;
+            }
+            Ok(arr)
+        }
+    }
+
+    /// Serializes elements in reverse order.
+    pub struct Reversed;
+
+    impl<T, const N: usize> ArrayCodec<T, N> for Reversed
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        fn serialize<S>(array: &[T; N], serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.collect_seq(array.iter().rev())
+        }
+
+        fn deserialize<'de, D>(deserializer: D) -> Result<[T; N], D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let mut elems = Vec::<T>::deserialize(deserializer)?;
+            elems.reverse();
+            elems
+                .try_into()
+                .map_err(|elems: Vec<T>| D::Error::invalid_length(elems.len(), &"an array"))
+        }
+    }
+}
+
+#[test]
+fn codec_roundtrip() {
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S<T> {
+        #[serbia(codec = "codecs::Hex")]
+        key: [u8; 40],
+        #[serbia(codec = "codecs::Reversed")]
+        generic: [T; 40],
+        #[serbia(codec = "codecs::Reversed")]
+        small: [i32; 3],
+    }
+
+    let mut original = S {
+        key: [0xab; 40],
+        generic: [0; 40],
+        small: [1, 2, 3],
+    };
+    original.key[1] = 0x01;
+    original.generic[0] = 7;
+
+    let serialized = serde_json::to_value(&original).unwrap();
+    assert!(serialized["key"].as_str().unwrap().starts_with("ab01abab"));
+    assert_eq!(serialized["generic"][39], 7);
+    assert_eq!(serialized["small"], serde_json::json!([3, 2, 1]));
+
+    let deserialized = serde_json::from_value(serialized).unwrap();
+    assert_eq!(original, deserialized);
+}
+
+#[test]
+fn codec_non_ascii_hex() {
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        #[serbia(codec = "codecs::Hex")]
+        key: [u8; 40],
+    }
+
+    // 80 bytes, but not 80 ASCII characters.
+    let hex = "é".repeat(40);
+    let deserialized: Result<S, _> = serde_json::from_value(serde_json::json!({ "key": hex }));
+    assert!(deserialized.is_err());
+}
+
+#[test]
+fn codec_tuple() {
+    #[serbia]
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct S {
+        default: [u16; 40],
+        #[serbia(codec = "serbia_codec::Tuple")]
+        codec: [u16; 40],
+    }
+
+    let mut original = S {
+        default: [3; 40],
+        codec: [3; 40],
+    };
+    original.default[39] = 1000;
+    original.codec[39] = 1000;
+
+    let serialized = bincode::serialize(&original).unwrap();
+    assert_eq!(serialized[..80], serialized[80..]);
+
+    let deserialized: S = bincode::deserialize(&serialized).unwrap();
+    assert_eq!(original, deserialized);
+}
@@ -0,0 +1,68 @@
+use serbia::serbia;
+use serde::{Deserialize, Serialize};
+
+#[serbia]
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Record {
+    id: u32,
+}
+
+#[serbia]
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct S {
+    records: [Record; 300],
+}
+
+fn records_json(bad_index: usize) -> String {
+    let mut records = vec![serde_json::json!({ "id": 1 }); 300];
+    records[bad_index] = serde_json::json!({ "id": "one" });
+
+    serde_json::json!({ "records": records }).to_string()
+}
+
+#[test]
+fn element_index_in_error() {
+    let err = serde_json::from_str::<S>(&records_json(183))
+        .unwrap_err()
+        .to_string();
+
+    assert!(
+        err.starts_with("invalid value at index 183 of array of length 300: invalid type"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn sequence_errors_keep_their_category() {
+    // Cut off between two elements, rather than in one.
+    let json = records_json(299);
+    let end_of_element = json.find("},").unwrap() + 1;
+    let err = serde_json::from_str::<S>(&json[..end_of_element]).unwrap_err();
+    assert!(err.is_eof(), "{}", err);
+
+    let err = serde_json::from_str::<S>(r#"{ "records": [{ "id": 1 } { "id": 1 }] }"#).unwrap_err();
+    assert!(err.is_syntax(), "{}", err);
+}
+
+#[test]
+fn element_index_in_error_path() {
+    let json = records_json(183);
+    let deserializer = &mut serde_json::Deserializer::from_str(&json);
+    let err = serde_path_to_error::deserialize::<_, S>(deserializer).unwrap_err();
+
+    assert_eq!(err.path().to_string(), "records[183].id");
+}
+
+#[test]
+fn expected_length_in_error() {
+    let err = serde_json::from_str::<S>(r#"{ "records": [{ "id": 1 }] }"#)
+        .unwrap_err()
+        .to_string();
+
+    assert!(
+        err.contains("invalid length 1, expected an array of length 300"),
+        "{}",
+        err
+    );
+}
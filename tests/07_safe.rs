@@ -0,0 +1,101 @@
+// Round trips and errors of fields with `#[serbia(safe)]`.
+#![forbid(unsafe_code)]
+
+use serbia::serbia;
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn safe_tuple() {
+    #[serbia]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct S {
+        #[serbia(safe)]
+        arr: [u16; 300],
+        #[serbia(safe)]
+        strings: [String; 40],
+    }
+
+    let s = S {
+        arr: [7; 300],
+        strings: std::array::from_fn(|i| i.to_string()),
+    };
+    let json = serde_json::to_string(&s).unwrap();
+    assert_eq!(serde_json::from_str::<S>(&json).unwrap(), s);
+
+    let bin = bincode::serialize(&s).unwrap();
+    assert_eq!(bincode::deserialize::<S>(&bin).unwrap(), s);
+}
+
+#[test]
+fn safe_generic() {
+    #[serbia]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct S<T> {
+        #[serbia(safe)]
+        arr: [T; 64],
+    }
+
+    let s = S { arr: [3u8; 64] };
+    let json = serde_json::to_string(&s).unwrap();
+    assert_eq!(serde_json::from_str::<S<u8>>(&json).unwrap(), s);
+}
+
+#[test]
+fn safe_with_element_options() {
+    fn not_zero(_: usize, e: &u32) -> Result<(), &'static str> {
+        if *e == 0 {
+            Err("zero")
+        } else {
+            Ok(())
+        }
+    }
+
+    #[serbia]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct S {
+        #[serbia(safe, validate_element = "not_zero")]
+        arr: [u32; 40],
+    }
+
+    let s = S { arr: [1; 40] };
+    let json = serde_json::to_string(&s).unwrap();
+    assert_eq!(serde_json::from_str::<S>(&json).unwrap(), s);
+
+    let mut bad = vec![1; 40];
+    bad[5] = 0;
+    let err = serde_json::from_value::<S>(serde_json::json!({ "arr": bad })).unwrap_err();
+    assert!(err.to_string().contains("index 5"), "{}", err);
+}
+
+#[test]
+fn safe_rows() {
+    #[serbia]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct S {
+        #[serbia(safe, rows = 8)]
+        arr: [u8; 64],
+    }
+
+    let s = S { arr: [9; 64] };
+    let json = serde_json::to_string(&s).unwrap();
+    assert_eq!(serde_json::from_str::<S>(&json).unwrap(), s);
+
+    let bin = bincode::serialize(&s).unwrap();
+    assert_eq!(bincode::deserialize::<S>(&bin).unwrap(), s);
+}
+
+#[test]
+fn safe_too_short() {
+    #[serbia]
+    #[derive(Deserialize, Debug)]
+    struct S {
+        #[serbia(safe)]
+        _arr: [String; 40],
+    }
+
+    let err = serde_json::from_value::<S>(serde_json::json!({
+        "_arr": ["a", "b", "c"]
+    }))
+    .unwrap_err();
+    assert!(err.to_string().contains("invalid length 3"), "{}", err);
+}
@@ -0,0 +1,151 @@
+use std::cell::Cell;
+
+use serbia::serbia;
+use serde::{Deserialize, Deserializer, Serialize};
+
+thread_local! {
+    // The deepest stack address an element was deserialized at.
+    static DEEPEST: Cell<usize> = const { Cell::new(usize::MAX) };
+}
+
+#[inline(never)]
+fn stack_address() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+/// An element that keeps track of how deep in the stack it's deserialized.
+#[derive(Serialize, Clone, Copy)]
+struct Probe(u8);
+
+impl<'de> Deserialize<'de> for Probe {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        DEEPEST.with(|deepest| deepest.set(deepest.get().min(stack_address())));
+        u8::deserialize(deserializer).map(Probe)
+    }
+}
+
+#[test]
+fn huge_array_stack_usage() {
+    #[allow(dead_code)]
+    #[serbia]
+    #[derive(Serialize, Deserialize)]
+    struct S {
+        probes: [Probe; 1 << 22],
+    }
+
+    let bin = vec![7u8; 1 << 22];
+
+    // The array itself is returned by value, which takes a few copies in debug builds.
+    std::thread::Builder::new()
+        .stack_size(32 << 20)
+        .spawn(move || {
+            let mut deserializer =
+                bincode::Deserializer::from_slice(&bin, bincode::DefaultOptions::new());
+            let top = stack_address();
+            // The generated function is called directly, since Serde's derived code
+            // keeps copies of its own.
+            let probes = serbia_deserialize_S_arr_0::<Probe, _>(&mut deserializer).unwrap();
+            let depth = top - DEEPEST.with(Cell::get);
+
+            assert_eq!(probes[5].0, 7);
+            // Only the return value should ever have room for the whole array.
+            assert!(depth < (1 << 22) / 2, "{}", depth);
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+// Arrays this big don't fit on the default 2 MiB thread stack by value, since Serde
+// moves them around. This only checks that they round-trip given a stack big enough
+// for that. Small stacks need the array boxed, see `boxed_array_on_small_stack`.
+#[test]
+fn huge_arrays_roundtrip() {
+    #[serbia]
+    #[derive(Serialize, Deserialize)]
+    struct S {
+        bytes: [u8; 1 << 20],
+        floats: [f64; 1 << 16],
+    }
+
+    std::thread::Builder::new()
+        .stack_size(64 << 20)
+        .spawn(|| {
+            let mut s: Box<S> = bincode::deserialize(&vec![0; (1 << 20) + (8 << 16)]).unwrap();
+            s.bytes[1000] = 7;
+            s.floats[1000] = 0.5;
+
+            let bin = bincode::serialize(&s).unwrap();
+            let s: Box<S> = bincode::deserialize(&bin).unwrap();
+            assert_eq!(s.bytes[1000], 7);
+            assert_eq!(s.floats[1000], 0.5);
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+#[test]
+fn boxed_array_on_small_stack() {
+    #[serbia]
+    #[derive(Serialize, Deserialize)]
+    struct S {
+        bytes: Box<[u8; 16_777_216]>,
+    }
+
+    // Far too small for the array, so it must never be on the stack.
+    std::thread::Builder::new()
+        .stack_size(256 << 10)
+        .spawn(|| {
+            let mut s: S = bincode::deserialize(&vec![0; 16_777_216]).unwrap();
+            s.bytes[1000] = 7;
+
+            let bin = bincode::serialize(&s).unwrap();
+            assert_eq!(bin.len(), 16_777_216);
+            let s: S = bincode::deserialize(&bin).unwrap();
+            assert_eq!(s.bytes[1000], 7);
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+#[test]
+fn rc_and_arc_arrays() {
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    fn not_empty(_: usize, s: &str) -> Result<(), &'static str> {
+        if s.is_empty() {
+            Err("empty")
+        } else {
+            Ok(())
+        }
+    }
+
+    #[serbia]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct S<T> {
+        #[serbia(validate_element = "not_empty")]
+        strings: Rc<[String; 40]>,
+        generic: Arc<[T; 64]>,
+        boxed: std::boxed::Box<[T; 33]>,
+    }
+
+    let s = S {
+        strings: Rc::new(std::array::from_fn(|i| i.to_string())),
+        generic: Arc::new([5u16; 64]),
+        boxed: Box::new([6; 33]),
+    };
+    let json = serde_json::to_string(&s).unwrap();
+    assert_eq!(serde_json::from_str::<S<u16>>(&json).unwrap(), s);
+
+    let mut value = serde_json::to_value(&s).unwrap();
+    value["strings"][3] = serde_json::json!("");
+    let err = serde_json::from_value::<S<u16>>(value).unwrap_err();
+    assert!(err.to_string().contains("index 3"), "{}", err);
+}
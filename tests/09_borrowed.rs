@@ -0,0 +1,249 @@
+use serbia::serbia;
+use serde::{Deserialize, Serialize};
+
+#[serbia]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Message<'a> {
+    id: u32,
+    payload: &'a [u8; 4096],
+    tag: &'a [u8; 16],
+}
+
+fn payload() -> [u8; 4096] {
+    std::array::from_fn(|i| i as u8)
+}
+
+#[test]
+fn borrowed_bincode() {
+    let payload = payload();
+    let msg = Message {
+        id: 7,
+        payload: &payload,
+        tag: &[3; 16],
+    };
+
+    let bin = bincode::serialize(&msg).unwrap();
+    let deserialized: Message = bincode::deserialize(&bin).unwrap();
+    assert_eq!(deserialized, msg);
+
+    // The array points into the input instead of being copied.
+    let input = bin.as_ptr_range();
+    assert!(input.contains(&deserialized.payload.as_ptr()));
+}
+
+#[test]
+fn borrowed_postcard() {
+    let payload = payload();
+    let msg = Message {
+        id: 7,
+        payload: &payload,
+        tag: &[3; 16],
+    };
+
+    let bin = postcard::to_allocvec(&msg).unwrap();
+    let deserialized: Message = postcard::from_bytes(&bin).unwrap();
+    assert_eq!(deserialized, msg);
+    assert!(bin.as_ptr_range().contains(&deserialized.payload.as_ptr()));
+}
+
+#[test]
+fn borrowed_wrong_length() {
+    #[derive(Serialize)]
+    struct Short<'a> {
+        id: u32,
+        #[serde(with = "serde_bytes_like")]
+        payload: &'a [u8],
+    }
+
+    mod serde_bytes_like {
+        pub fn serialize<S: serde::Serializer>(v: &[u8], s: S) -> Result<S::Ok, S::Error> {
+            s.serialize_bytes(v)
+        }
+    }
+
+    let bin = bincode::serialize(&Short {
+        id: 7,
+        payload: &[1; 4000],
+    })
+    .unwrap();
+    let err = bincode::deserialize::<Message>(&bin).unwrap_err();
+    assert!(err.to_string().contains("invalid length 4000"), "{}", err);
+}
+
+#[test]
+fn borrowed_unsupported_format() {
+    // JSON can't hand out borrowed bytes.
+    let json = serde_json::json!({
+        "id": 7,
+        "payload": vec![1; 4096],
+        "tag": vec![1; 16],
+    })
+    .to_string();
+    assert!(serde_json::from_str::<Message>(&json).is_err());
+}
+
+#[test]
+fn explicit_borrow() {
+    #[serbia]
+    #[derive(Serialize, Deserialize)]
+    struct S<'a> {
+        #[serde(borrow)]
+        payload: &'a [u8; 64],
+    }
+
+    let bin = bincode::serialize(&S { payload: &[9; 64] }).unwrap();
+    let s: S = bincode::deserialize(&bin).unwrap();
+    assert_eq!(s.payload, &[9; 64]);
+}
+
+#[test]
+fn serialize_reference_to_array() {
+    #[serbia]
+    #[derive(Serialize)]
+    struct S<'a> {
+        values: &'a [u32; 40],
+    }
+
+    let values = [5; 40];
+    let json = serde_json::to_value(S { values: &values }).unwrap();
+    assert_eq!(json, serde_json::json!({ "values": vec![5; 40] }));
+}
+
+#[test]
+fn serialize_only_small_byte_array_reference() {
+    #[serbia]
+    #[derive(Serialize)]
+    struct S<'a> {
+        bytes: &'a [u8; 4],
+    }
+
+    #[derive(Serialize)]
+    struct Plain<'a> {
+        bytes: &'a [u8; 4],
+    }
+
+    // Left to Serde, which serializes it as a tuple, without a length prefix.
+    let bin = bincode::serialize(&S {
+        bytes: &[1, 2, 3, 4],
+    })
+    .unwrap();
+    assert_eq!(
+        bin,
+        bincode::serialize(&Plain {
+            bytes: &[1, 2, 3, 4]
+        })
+        .unwrap()
+    );
+    assert_eq!(bin, [1, 2, 3, 4]);
+}
+
+#[test]
+fn borrowed_str_elements() {
+    fn not_empty(_: usize, s: &&str) -> Result<(), &'static str> {
+        if s.is_empty() {
+            Err("empty")
+        } else {
+            Ok(())
+        }
+    }
+
+    fn sorted(strs: &[&str; 40]) -> Result<(), &'static str> {
+        if strs.windows(2).all(|w| w[0] <= w[1]) {
+            Ok(())
+        } else {
+            Err("not sorted")
+        }
+    }
+
+    #[serbia]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct S<'a, T> {
+        strs: [&'a str; 40],
+        #[serbia(validate_element = "not_empty", validate = "sorted")]
+        checked: [&'a str; 40],
+        generic: [T; 40],
+    }
+
+    let names: Vec<String> = (10..50).map(|i| i.to_string()).collect();
+    let strs: [&str; 40] = std::array::from_fn(|i| names[i].as_str());
+    let s = S {
+        strs,
+        checked: strs,
+        generic: [1u8; 40],
+    };
+
+    let json = serde_json::to_string(&s).unwrap();
+    assert_eq!(serde_json::from_str::<S<u8>>(&json).unwrap(), s);
+
+    let bin = bincode::serialize(&s).unwrap();
+    let deserialized: S<u8> = bincode::deserialize(&bin).unwrap();
+    assert_eq!(deserialized, s);
+    assert!(bin.as_ptr_range().contains(&deserialized.strs[0].as_ptr()));
+
+    let mut unsorted = s.checked;
+    unsorted.swap(0, 1);
+    let bin = bincode::serialize(&S {
+        checked: unsorted,
+        ..s
+    })
+    .unwrap();
+    let err = bincode::deserialize::<S<u8>>(&bin).unwrap_err();
+    assert!(err.to_string().contains("not sorted"), "{}", err);
+}
+
+#[test]
+fn borrowed_cow_elements() {
+    use std::borrow::Cow;
+
+    #[serbia]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct S<'a> {
+        #[serde(borrow)]
+        strs: [Cow<'a, str>; 40],
+        #[serde(borrow)]
+        bytes: [Cow<'a, [u8]>; 40],
+        owned: [Cow<'a, str>; 40],
+    }
+
+    let s = S {
+        strs: std::array::from_fn(|i| Cow::Owned(i.to_string())),
+        bytes: std::array::from_fn(|i| Cow::Owned(vec![i as u8; 3])),
+        owned: std::array::from_fn(|i| Cow::Owned(i.to_string())),
+    };
+
+    let bin = bincode::serialize(&s).unwrap();
+    let deserialized: S = bincode::deserialize(&bin).unwrap();
+    assert_eq!(deserialized, s);
+    assert!(matches!(deserialized.strs[3], Cow::Borrowed(_)));
+    assert!(matches!(deserialized.bytes[3], Cow::Borrowed(_)));
+    assert!(matches!(deserialized.owned[3], Cow::Owned(_)));
+
+    // Escaped strings can't be borrowed from JSON.
+    let mut value = serde_json::to_value(&s).unwrap();
+    value["strs"][5] = serde_json::json!("a\nb");
+    let json = value.to_string();
+    let deserialized: S = serde_json::from_str(&json).unwrap();
+    assert!(matches!(deserialized.strs[4], Cow::Borrowed(_)));
+    assert_eq!(deserialized.strs[5], Cow::<str>::Owned("a\nb".to_string()));
+    assert_eq!(deserialized.bytes, s.bytes);
+}
+
+#[test]
+fn owned_cow_elements() {
+    use std::borrow::Cow;
+
+    #[serbia]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct S<'a> {
+        strs: [Cow<'a, str>; 40],
+    }
+
+    let s = S {
+        strs: std::array::from_fn(|i| Cow::Owned(i.to_string())),
+    };
+    let json = serde_json::to_vec(&s).unwrap();
+
+    // Without `#[serde(borrow)]`, nothing is borrowed and the input can go away.
+    let deserialized: S<'static> = serde_json::from_reader(json.as_slice()).unwrap();
+    assert_eq!(deserialized, s);
+}
@@ -0,0 +1,228 @@
+// Round trips and errors of tuples with more elements than Serde supports. The
+// standard library doesn't implement `Debug` or `PartialEq` for them either, so
+// round trips are checked by serializing again.
+
+#![allow(clippy::type_complexity)]
+
+use serbia::serbia;
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn struct_field() {
+    #[serbia]
+    #[derive(Serialize, Deserialize)]
+    struct S {
+        tuple: (
+            u8,
+            u16,
+            u32,
+            u64,
+            i8,
+            i16,
+            i32,
+            i64,
+            f32,
+            f64,
+            bool,
+            char,
+            String,
+            u8,
+            u8,
+            u8,
+            u8,
+        ),
+        small: (u8, u8),
+    }
+
+    let s = S {
+        tuple: (
+            1,
+            2,
+            3,
+            4,
+            -5,
+            -6,
+            -7,
+            -8,
+            9.5,
+            10.5,
+            true,
+            'c',
+            "a".to_string(),
+            14,
+            15,
+            16,
+            17,
+        ),
+        small: (1, 2),
+    };
+
+    let json = serde_json::to_string(&s).unwrap();
+    assert_eq!(
+        json,
+        r#"{"tuple":[1,2,3,4,-5,-6,-7,-8,9.5,10.5,true,"c","a",14,15,16,17],"small":[1,2]}"#
+    );
+    let de = serde_json::from_str::<S>(&json).unwrap();
+    assert_eq!(serde_json::to_string(&de).unwrap(), json);
+    assert_eq!(de.tuple.12, "a");
+
+    let bin = bincode::serialize(&s).unwrap();
+    let de = bincode::deserialize::<S>(&bin).unwrap();
+    assert_eq!(bincode::serialize(&de).unwrap(), bin);
+}
+
+#[test]
+fn tuple_struct_and_variants() {
+    #[serbia]
+    #[derive(Serialize, Deserialize)]
+    struct T(
+        (
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+        ),
+    );
+
+    #[serbia]
+    #[derive(Serialize, Deserialize)]
+    enum E {
+        A(
+            (
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+            ),
+            u8,
+        ),
+        B {
+            tuple: (
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+                u8,
+            ),
+        },
+    }
+
+    let t = T((1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17));
+    let json = serde_json::to_string(&t).unwrap();
+    assert_eq!(json, "[1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17]");
+    let de = serde_json::from_str::<T>(&json).unwrap();
+    assert_eq!(serde_json::to_string(&de).unwrap(), json);
+
+    for e in [
+        E::A(
+            (0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16),
+            17,
+        ),
+        E::B {
+            tuple: (0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17),
+        },
+    ] {
+        let json = serde_json::to_string(&e).unwrap();
+        let de = serde_json::from_str::<E>(&json).unwrap();
+        assert_eq!(serde_json::to_string(&de).unwrap(), json);
+
+        let bin = postcard::to_allocvec(&e).unwrap();
+        let de = postcard::from_bytes::<E>(&bin).unwrap();
+        assert_eq!(postcard::to_allocvec(&de).unwrap(), bin);
+    }
+}
+
+#[test]
+fn generic_elements() {
+    #[serbia]
+    #[derive(Serialize, Deserialize)]
+    struct S<T, U> {
+        tuple: (T, U, T, U, T, U, T, U, T, U, T, U, T, U, T, U, T),
+    }
+
+    let s = S {
+        tuple: (
+            1u8, 'a', 2, 'b', 3, 'c', 4, 'd', 5, 'e', 6, 'f', 7, 'g', 8, 'h', 9,
+        ),
+    };
+
+    let json = serde_json::to_string(&s).unwrap();
+    let de = serde_json::from_str::<S<u8, char>>(&json).unwrap();
+    assert_eq!(serde_json::to_string(&de).unwrap(), json);
+}
+
+#[test]
+fn too_short() {
+    #[serbia]
+    #[derive(Deserialize)]
+    struct S {
+        _tuple: (
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+        ),
+    }
+
+    let err = serde_json::from_str::<S>(r#"{"_tuple":[1,2,3]}"#)
+        .err()
+        .unwrap();
+    assert!(
+        err.to_string()
+            .contains("invalid length 3, expected a tuple of size 17"),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,75 @@
+// Derived `Default` for types with big arrays.
+
+use serbia::serbia;
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn derived_default() {
+    #[serbia]
+    #[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+    struct S {
+        arr: [u8; 300],
+        name: String,
+    }
+
+    let s = S::default();
+    assert_eq!(s.arr, [0; 300]);
+    assert_eq!(s.name, "");
+}
+
+#[test]
+fn nested_arrays() {
+    #[serbia]
+    #[derive(Default)]
+    struct S {
+        nested: [[u16; 40]; 2],
+    }
+
+    assert_eq!(S::default().nested, [[0; 40]; 2]);
+}
+
+#[test]
+fn tuple_struct() {
+    const LEN: usize = 64;
+
+    #[serbia]
+    #[derive(Default)]
+    struct S([Option<u32>; LEN], u8);
+
+    let s = S::default();
+    assert_eq!(s.0, [None; LEN]);
+    assert_eq!(s.1, 0);
+}
+
+#[test]
+fn inferred_bounds() {
+    // Doesn't implement `Default`, but `Vec<T>` does.
+    #[derive(Debug, PartialEq)]
+    struct NoDefault;
+
+    #[serbia]
+    #[derive(Default)]
+    struct S<T, U> {
+        arr: [T; 40],
+        list: Vec<U>,
+    }
+
+    let s = S::<u8, NoDefault>::default();
+    assert_eq!(s.arr, [0; 40]);
+    assert_eq!(s.list, vec![]);
+}
+
+#[test]
+fn container_serde_default() {
+    #[serbia]
+    #[derive(Deserialize, Default, Debug, PartialEq)]
+    #[serde(default)]
+    struct S {
+        arr: [u8; 40],
+        other: u8,
+    }
+
+    let s: S = serde_json::from_str(r#"{"other":5}"#).unwrap();
+    assert_eq!(s.arr, [0; 40]);
+    assert_eq!(s.other, 5);
+}
@@ -0,0 +1,79 @@
+// Serde's field-level `default` on big arrays, and fill values from `#[serbia(default)]`.
+
+use std::rc::Rc;
+
+use serbia::serbia;
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn bare_serde_default() {
+    #[serbia]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct S {
+        #[serde(default)]
+        arr: [u8; 300],
+        #[serde(default)]
+        strings: Box<[String; 40]>,
+        #[serde(skip, default)]
+        skipped: [u16; 40],
+        other: u8,
+    }
+
+    let s: S = serde_json::from_str(r#"{"other":5}"#).unwrap();
+    assert_eq!(s.arr, [0; 300]);
+    assert!(s.strings.iter().all(String::is_empty));
+    assert_eq!(s.skipped, [0; 40]);
+
+    let json = serde_json::to_string(&S { arr: [1; 300], ..s }).unwrap();
+    assert_eq!(serde_json::from_str::<S>(&json).unwrap().arr, [1; 300]);
+}
+
+#[test]
+fn fill_value() {
+    #[serbia]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct S {
+        #[serbia(default = 0xFF)]
+        arr: [u8; 300],
+        #[serbia(default = 7)]
+        small: [u32; 4],
+        #[serde(default)]
+        #[serbia(default = "u16::MAX")]
+        expr: Rc<[u16; 40]>,
+    }
+
+    let s: S = serde_json::from_str("{}").unwrap();
+    assert_eq!(s.arr, [0xFF; 300]);
+    assert_eq!(s.small, [7; 4]);
+    assert_eq!(*s.expr, [u16::MAX; 40]);
+}
+
+#[test]
+#[allow(clippy::type_complexity)]
+fn generic_elements() {
+    #[serbia]
+    #[derive(Serialize, Deserialize)]
+    struct S<T, U> {
+        #[serde(default)]
+        arr: [T; 40],
+        #[serde(default)]
+        tuple: (U, U, U, U, U, U, U, U, U, U, U, U, U, U, U, U, U),
+    }
+
+    let s: S<u8, bool> = serde_json::from_str("{}").unwrap();
+    assert_eq!(s.arr, [0; 40]);
+    assert!(!s.tuple.16);
+}
+
+#[test]
+fn skipped_generic() {
+    #[serbia]
+    #[derive(Serialize, Deserialize)]
+    struct S<T> {
+        #[serde(skip, default)]
+        arr: [T; 40],
+    }
+
+    let s: S<u8> = serde_json::from_str("{}").unwrap();
+    assert_eq!(s.arr, [0; 40]);
+}
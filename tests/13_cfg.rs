@@ -0,0 +1,128 @@
+// Derives and fields that are conditional on `cfg` predicates. `test` stands in for
+// a feature that's on, and `not(test)` for one that's off.
+
+#![deny(warnings)]
+
+use serbia::serbia;
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn cfg_attr_derives_on() {
+    #[serbia]
+    #[derive(Debug, PartialEq)]
+    #[cfg_attr(test, derive(Serialize, Deserialize))]
+    struct S {
+        arr: [u8; 300],
+        #[cfg_attr(test, serde(default))]
+        defaulted: [u16; 40],
+    }
+
+    let s = S {
+        arr: [1; 300],
+        defaulted: [0; 40],
+    };
+    let json = serde_json::to_string(&s).unwrap();
+    assert_eq!(serde_json::from_str::<S>(&json).unwrap(), s);
+
+    let s: S = serde_json::from_value(serde_json::json!({ "arr": vec![1; 300] })).unwrap();
+    assert_eq!(s.defaulted, [0; 40]);
+}
+
+#[test]
+fn cfg_attr_derives_off() {
+    #[serbia]
+    #[derive(Debug, PartialEq)]
+    #[cfg_attr(not(test), derive(Serialize, Deserialize))]
+    struct S {
+        arr: [u8; 300],
+        #[cfg_attr(not(test), serde(default))]
+        defaulted: [u16; 40],
+    }
+
+    let s = S {
+        arr: [1; 300],
+        defaulted: [0; 40],
+    };
+    assert_eq!(s.arr[0], 1);
+    assert_eq!(s.defaulted[0], 0);
+}
+
+#[test]
+fn nested_and_mixed_cfg_attr() {
+    #[serbia]
+    #[derive(Serialize)]
+    #[cfg_attr(test, cfg_attr(test, derive(Deserialize)))]
+    struct S<T> {
+        arr: [T; 40],
+    }
+
+    let s = S { arr: [5u8; 40] };
+    let json = serde_json::to_string(&s).unwrap();
+    assert_eq!(serde_json::from_str::<S<u8>>(&json).unwrap().arr, s.arr);
+}
+
+#[test]
+fn cfg_field() {
+    #[serbia]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum E {
+        A {
+            #[cfg(not(test))]
+            arr: [u8; 300],
+            #[cfg(test)]
+            other: [u8; 40],
+        },
+    }
+
+    let e = E::A { other: [3; 40] };
+    let json = serde_json::to_string(&e).unwrap();
+    assert_eq!(serde_json::from_str::<E>(&json).unwrap(), e);
+}
+
+#[test]
+fn cfg_field_default() {
+    #[serbia]
+    #[derive(Default, Debug, PartialEq)]
+    struct S {
+        #[cfg(not(test))]
+        off: [u8; 40],
+        #[cfg(test)]
+        on: [u8; 40],
+    }
+
+    #[serbia]
+    #[derive(Default, Debug, PartialEq)]
+    struct T(#[cfg(not(test))] [u8; 40], #[cfg(test)] [u16; 40]);
+
+    assert_eq!(S::default(), S { on: [0; 40] });
+    assert_eq!(T::default(), T([0; 40]));
+}
+
+#[test]
+fn cfg_attr_default() {
+    #[serbia]
+    #[derive(Debug, PartialEq)]
+    #[cfg_attr(test, derive(Default, Serialize, Deserialize))]
+    struct On {
+        arr: [u8; 300],
+    }
+
+    #[serbia]
+    #[derive(Debug, PartialEq)]
+    #[cfg_attr(not(test), derive(Default, Serialize, Deserialize))]
+    struct Off {
+        arr: [u8; 300],
+    }
+
+    #[serbia]
+    #[derive(Debug)]
+    #[cfg_attr(test, derive(Default))]
+    #[cfg_attr(test, derive(PartialEq))]
+    struct Both {
+        arr: [u8; 300],
+    }
+
+    assert_eq!(On::default(), On { arr: [0; 300] });
+    assert_eq!(Off { arr: [1; 300] }.arr[0], 1);
+    assert_eq!(Both::default(), Both { arr: [0; 300] });
+}
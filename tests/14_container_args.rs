@@ -0,0 +1,90 @@
+// Options given to `#[serbia(...)]` on the container.
+
+use serbia::serbia;
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn threshold() {
+    #[serbia(threshold = 16, delta)]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct S {
+        small: [u32; 16],
+        medium: [u32; 17],
+        big: [u32; 33],
+    }
+
+    let s = S {
+        small: [1; 16],
+        medium: [1; 17],
+        big: [1; 33],
+    };
+    let json = serde_json::to_string(&s).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+    // Delta encoded arrays are all zeros after the first element.
+    assert_eq!(value["small"][1], 1);
+    assert_eq!(value["medium"][1], 0);
+    assert_eq!(value["big"][1], 0);
+    assert_eq!(serde_json::from_str::<S>(&json).unwrap(), s);
+}
+
+#[test]
+fn all_arrays() {
+    #[serbia(all, packed = "le")]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct S {
+        tiny: [u16; 2],
+        big: [u16; 40],
+    }
+
+    let s = S {
+        tiny: [1, 2],
+        big: [3; 40],
+    };
+    let json = serde_json::to_string(&s).unwrap();
+    assert!(json.starts_with(r#"{"tiny":[1,0,2,0],"#), "{}", json);
+    assert_eq!(serde_json::from_str::<S>(&json).unwrap(), s);
+}
+
+#[test]
+fn field_overrides() {
+    #[serbia(delta)]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct S {
+        ints: [u32; 40],
+        #[serbia(tuple)]
+        strings: [String; 40],
+        #[serbia(packed = "be")]
+        packed: [u16; 40],
+    }
+
+    let s = S {
+        ints: [5; 40],
+        strings: std::array::from_fn(|i| i.to_string()),
+        packed: [1; 40],
+    };
+    let json = serde_json::to_string(&s).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(value["ints"][1], 0);
+    assert_eq!(value["packed"][1], 1);
+    assert_eq!(serde_json::from_str::<S>(&json).unwrap(), s);
+}
+
+mod aliased {
+    pub use serde::Deserialize as De;
+    pub use serde::Serialize as Ser;
+}
+
+#[test]
+fn forced_generation() {
+    #[serbia(serialize, deserialize)]
+    #[derive(aliased::Ser, aliased::De, Debug, PartialEq)]
+    struct S {
+        arr: [u8; 300],
+    }
+
+    let s = S { arr: [4; 300] };
+    let json = serde_json::to_string(&s).unwrap();
+    assert_eq!(serde_json::from_str::<S>(&json).unwrap(), s);
+}
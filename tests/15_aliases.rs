@@ -0,0 +1,162 @@
+// Type aliases of arrays declared on the container, and arrays in an `Option`.
+
+use serbia::serbia;
+use serde::{Deserialize, Serialize};
+
+const BLOCK_LEN: usize = 48;
+
+type Key = [u8; 64];
+type Block = [u32; BLOCK_LEN];
+
+#[test]
+fn aliases() {
+    #[serbia(alias(Key = 64, Block = "BLOCK_LEN"))]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct S {
+        key: Key,
+        block: Block,
+        maybe_key: Option<Key>,
+        no_key: Option<Key>,
+    }
+
+    let s = S {
+        key: [1; 64],
+        block: [2; BLOCK_LEN],
+        maybe_key: Some([3; 64]),
+        no_key: None,
+    };
+
+    let json = serde_json::to_string(&s).unwrap();
+    assert_eq!(serde_json::from_str::<S>(&json).unwrap(), s);
+
+    let bin = bincode::serialize(&s).unwrap();
+    assert_eq!(bincode::deserialize::<S>(&bin).unwrap(), s);
+}
+
+#[test]
+fn optional_arrays() {
+    #[serbia]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct S {
+        #[serde(default)]
+        arr: Option<[u16; 300]>,
+        #[serbia(delta)]
+        deltas: Option<[u16; 40]>,
+        boxed: Option<Box<[u8; 40]>>,
+    }
+
+    let s = S {
+        arr: Some([1; 300]),
+        deltas: Some([5; 40]),
+        boxed: None,
+    };
+    let json = serde_json::to_string(&s).unwrap();
+    assert!(json.contains(r#""deltas":[5,0,0"#), "{}", json);
+    assert!(json.contains(r#""boxed":null"#), "{}", json);
+    assert_eq!(serde_json::from_str::<S>(&json).unwrap(), s);
+
+    let s: S = serde_json::from_str(r#"{"deltas":null,"boxed":null}"#).unwrap();
+    assert_eq!(s.arr, None);
+
+    let bin = postcard::to_allocvec(&s).unwrap();
+    assert_eq!(postcard::from_bytes::<S>(&bin).unwrap(), s);
+}
+
+#[test]
+fn missing_optional_arrays() {
+    #[serbia(alias(Key = 64))]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct S {
+        maybe_key: Option<Key>,
+        arr: Option<[u8; 40]>,
+    }
+
+    #[serbia]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct T(u8, Option<[u8; 40]>);
+
+    #[serbia]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum E {
+        V { arr: Option<[u8; 40]> },
+    }
+
+    let s: S = serde_json::from_str("{}").unwrap();
+    assert_eq!(
+        s,
+        S {
+            maybe_key: None,
+            arr: None
+        }
+    );
+
+    let t: T = serde_json::from_str("[1]").unwrap();
+    assert_eq!(t, T(1, None));
+
+    let e: E = serde_json::from_str(r#"{"V":{}}"#).unwrap();
+    assert_eq!(e, E::V { arr: None });
+}
+
+#[test]
+fn generic_optional_arrays_with_serde_with() {
+    mod opt {
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        pub fn serialize<T, S>(value: &Option<[T; 40]>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            T: Serialize,
+            S: Serializer,
+        {
+            value.as_ref().map(|arr| &arr[..]).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<[T; 40]>, D::Error>
+        where
+            T: Deserialize<'de>,
+            D: Deserializer<'de>,
+        {
+            use serde::de::Error;
+            use std::convert::TryInto;
+
+            Option::<Vec<T>>::deserialize(deserializer)?
+                .map(|elems| {
+                    elems
+                        .try_into()
+                        .map_err(|elems: Vec<T>| D::Error::invalid_length(elems.len(), &"40"))
+                })
+                .transpose()
+        }
+    }
+
+    #[serbia]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct S<T> {
+        #[serde(with = "opt")]
+        #[serde(bound(serialize = "T: Serialize", deserialize = "T: Deserialize<'de>"))]
+        arr: Option<[T; 40]>,
+    }
+
+    let s = S { arr: Some([7; 40]) };
+    let json = serde_json::to_string(&s).unwrap();
+    assert_eq!(serde_json::from_str::<S<u8>>(&json).unwrap(), s);
+}
+
+#[test]
+fn codec_with_declared_length() {
+    #[serbia(alias(Key = 64))]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct S {
+        #[serbia(codec = "serbia_codec::Tuple")]
+        aliased: Key,
+        #[serbia(bufsize = 64, codec = "serbia_codec::Tuple")]
+        sized: Key,
+    }
+
+    let s = S {
+        aliased: [1; 64],
+        sized: [2; 64],
+    };
+
+    let bin = bincode::serialize(&s).unwrap();
+    assert_eq!(bincode::deserialize::<S>(&bin).unwrap(), s);
+}
@@ -0,0 +1,57 @@
+// Deserializing big arrays into existing ones with serbia_codec::deserialize_in_place.
+
+use bincode::Options;
+use serbia::serbia;
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn frames_in_place() {
+    #[serbia]
+    #[derive(Serialize, Deserialize)]
+    struct Frame {
+        pixels: [u32; 76800],
+    }
+
+    let mut frame = Frame { pixels: [0; 76800] };
+    for i in 0..3 {
+        let mut pixels = [i; 76800];
+        pixels[1000] = 7;
+        let bin = bincode::DefaultOptions::new()
+            .serialize(&Frame { pixels })
+            .unwrap();
+
+        let mut deserializer =
+            bincode::Deserializer::from_slice(&bin, bincode::DefaultOptions::new());
+        serbia_codec::deserialize_in_place(&mut frame.pixels, &mut deserializer).unwrap();
+
+        assert_eq!(frame.pixels[0], i);
+        assert_eq!(frame.pixels[1000], 7);
+    }
+}
+
+#[test]
+fn elements_reuse_allocations() {
+    let mut arr: [Vec<u8>; 40] = std::array::from_fn(|_| Vec::with_capacity(16));
+    let ptrs: Vec<_> = arr.iter().map(|v| v.as_ptr()).collect();
+
+    let json = serde_json::to_string(&vec![vec![1u8, 2, 3]; 40]).unwrap();
+    let mut deserializer = serde_json::Deserializer::from_str(&json);
+    serbia_codec::deserialize_in_place(&mut arr, &mut deserializer).unwrap();
+
+    assert!(arr.iter().all(|v| v == &[1, 2, 3]));
+    assert!(arr.iter().zip(ptrs).all(|(v, ptr)| v.as_ptr() == ptr));
+}
+
+#[test]
+fn in_place_wrong_length() {
+    let mut arr = [0u16; 40];
+
+    let json = serde_json::to_string(&vec![1u16; 39]).unwrap();
+    let mut deserializer = serde_json::Deserializer::from_str(&json);
+    let err = serbia_codec::deserialize_in_place(&mut arr, &mut deserializer).unwrap_err();
+    assert!(
+        err.to_string().contains("expected an array of length 40"),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,7 @@
+// Compile errors, checked against the expected compiler output in tests/ui.
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
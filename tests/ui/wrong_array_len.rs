@@ -0,0 +1,21 @@
+use serbia::serbia;
+use serde::{Deserialize, Serialize};
+
+type BigArray = [i32; 256];
+
+#[serbia]
+#[derive(Serialize, Deserialize)]
+struct S {
+    #[serbia(bufsize = 300)]
+    arr_a: BigArray,
+    #[serbia(bufsize = 300)]
+    arr_b: Option<BigArray>,
+}
+
+#[serbia(alias(BigArray = 300))]
+#[derive(Serialize, Deserialize)]
+struct T {
+    arr: BigArray,
+}
+
+fn main() {}